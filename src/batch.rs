@@ -0,0 +1,204 @@
+use crate::board::{Board, Heuristics};
+use crate::goal::Goal;
+use crate::solver::{Algo, CancelToken, SearchLimits, SolveResult, Solver};
+
+use std::collections::BTreeMap;
+use std::sync::{Mutex, mpsc};
+use std::thread;
+
+// One puzzle of a batch, solved towards its own goal
+#[derive(Clone, Debug)]
+pub struct BatchPuzzle {
+    pub board: Board,
+    pub goal: Goal,
+}
+
+// Solves many puzzles on a pool of worker threads, every puzzle with its own Solver and limits
+pub struct BatchSolver {
+    algo: Algo,
+    heuristic: Heuristics,
+    limits: SearchLimits,
+    workers: usize,
+    // threads of every Solver, for Algo::PARALLELIDASTAR
+    threads: usize,
+    cancel_token: Option<CancelToken>,
+}
+
+impl BatchSolver {
+    pub fn new(algo: Algo, heuristic: Heuristics) -> Self {
+        BatchSolver {
+            algo,
+            heuristic,
+            limits: SearchLimits::default(),
+            workers: thread::available_parallelism().map_or(1, |n| n.get()),
+            threads: 1,
+            cancel_token: None,
+        }
+    }
+
+    // Limits of every single puzzle, the batch itself has none
+    pub fn set_limits(&mut self, limits: SearchLimits) {
+        self.limits = limits;
+    }
+
+    pub fn set_workers(&mut self, workers: usize) {
+        self.workers = workers.max(1);
+    }
+
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    // Cancels every puzzle being solved, the ones left are reported as Outcome::Cancelled
+    pub fn set_cancel_token(&mut self, cancel_token: CancelToken) {
+        self.cancel_token = Some(cancel_token);
+    }
+
+    // Calls on_result with the index and the result of every puzzle, in the order of the
+    // puzzles, as soon as it and all the previous ones are solved. The puzzles are only taken
+    // from the iterator when a worker is free, so they can be read while the batch is solved.
+    pub fn solve_each(
+        &self,
+        puzzles: impl IntoIterator<Item = BatchPuzzle, IntoIter: Send>,
+        mut on_result: impl FnMut(usize, SolveResult),
+    ) {
+        let puzzles = puzzles.into_iter();
+        let workers = puzzles
+            .size_hint()
+            .1
+            .map_or(self.workers, |count| count.min(self.workers));
+        // the next puzzle with its index
+        let next_puzzle = Mutex::new(puzzles.enumerate());
+        let (sender, receiver) = mpsc::channel();
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                let sender = sender.clone();
+                let next_puzzle = &next_puzzle;
+                scope.spawn(move || {
+                    loop {
+                        let next = next_puzzle.lock().unwrap().next();
+                        let Some((index, puzzle)) = next else {
+                            break;
+                        };
+                        if sender.send((index, self.solve_one(puzzle))).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(sender);
+
+            // results arriving out of order wait until the previous ones are reported
+            let mut pending = BTreeMap::new();
+            let mut next_result = 0;
+            for (index, result) in receiver {
+                pending.insert(index, result);
+                while let Some(result) = pending.remove(&next_result) {
+                    on_result(next_result, result);
+                    next_result += 1;
+                }
+            }
+        });
+    }
+
+    // Results of every puzzle, in the same order
+    pub fn solve_all(&self, puzzles: &[BatchPuzzle]) -> Vec<SolveResult> {
+        let mut results = Vec::with_capacity(puzzles.len());
+        self.solve_each(puzzles.iter().cloned(), |_, result| results.push(result));
+        results
+    }
+
+    fn solve_one(&self, puzzle: BatchPuzzle) -> SolveResult {
+        let mut solver = Solver::with_goal(puzzle.goal);
+        solver.set_limits(self.limits);
+        solver.set_threads(self.threads);
+        if let Some(cancel_token) = &self.cancel_token {
+            solver.set_cancel_token(cancel_token.clone());
+        }
+        solver.solve(puzzle.board, self.algo, self.heuristic)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::PuzzleState;
+    use crate::solver::{Limit, Outcome};
+
+    fn puzzle(puzzle_str: &str) -> BatchPuzzle {
+        let board: Board = puzzle_str.parse().unwrap();
+        let goal = Goal::standard(board.get_rows(), board.get_cols());
+        BatchPuzzle { board, goal }
+    }
+
+    #[test]
+    fn solve_all_test() {
+        let puzzles = vec![
+            puzzle("3\n8 6 7\n2 5 4\n3 0 1"),
+            puzzle("3\n1 2 3\n4 5 6\n7 8 0"),
+            puzzle("3\n1 2 3\n4 5 6\n8 7 0"),
+            puzzle("2 3\n1 2 3\n4 0 5"),
+            puzzle("3\n0 1 3\n4 2 5\n7 8 6"),
+        ];
+        let mut batch = BatchSolver::new(Algo::IDASTAR, Heuristics::LINCONFLICT);
+        batch.set_workers(3);
+        let results = batch.solve_all(&puzzles);
+        let outcomes: Vec<_> = results.iter().map(|r| (r.outcome, r.cost)).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                (Outcome::Solved, 31),
+                (Outcome::Solved, 0),
+                (Outcome::Unsolvable, 0),
+                (Outcome::Solved, 1),
+                (Outcome::Solved, 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn limits_test() {
+        let puzzles = vec![
+            puzzle("3\n8 6 7\n2 5 4\n3 0 1"),
+            puzzle("3\n0 1 3\n4 2 5\n7 8 6"),
+        ];
+        let mut batch = BatchSolver::new(Algo::ASTAR, Heuristics::MANHATTAN);
+        batch.set_limits(SearchLimits {
+            max_nodes: Some(100),
+            ..Default::default()
+        });
+        let mut indexes = vec![];
+        // the puzzles can come from any iterator
+        batch.solve_each(puzzles.into_iter().rev(), |index, result| {
+            indexes.push(index);
+            let expected = match index {
+                0 => Outcome::Solved,
+                _ => Outcome::LimitReached(Limit::MaxNodes),
+            };
+            assert_eq!(result.outcome, expected);
+        });
+        assert_eq!(indexes, vec![0, 1]);
+    }
+
+    #[test]
+    fn streaming_test() {
+        // every puzzle is only sent once the previous one is solved, which would never end if
+        // the batch waited for all of them
+        let (sender, receiver) = mpsc::channel();
+        sender.send(puzzle("3\n1 2 3\n4 0 5\n7 8 6")).unwrap();
+        let mut sender = Some(sender);
+        let batch = BatchSolver::new(Algo::IDASTAR, Heuristics::MANHATTAN);
+        let mut count = 0;
+        batch.solve_each(receiver, |_, result| {
+            assert_eq!(result.cost, 2);
+            count += 1;
+            if count < 5 {
+                let sender = sender.as_ref().unwrap();
+                sender.send(puzzle("3\n1 2 3\n4 0 5\n7 8 6")).unwrap();
+            } else {
+                sender = None;
+            }
+        });
+        assert_eq!(count, 5);
+    }
+}
@@ -1,14 +1,33 @@
 use core::fmt;
 use grid::*;
 
-#[derive(Clone, Hash, Eq)]
+use crate::goal::Goal;
+use crate::pattern_db::{self, PatternDatabase};
+use crate::ranking;
+use crate::walking_distance::WalkingDistance;
+use std::hash::Hash;
+use std::str::FromStr;
+use std::sync::Arc;
+
+#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "BoardRows", into = "BoardRows")
+)]
 pub struct Board {
     grid: Grid<usize>,
-    n: usize,
+    rows: usize,
+    cols: usize,
     pos_0: (usize, usize),
 }
 
-#[derive(PartialEq, Eq, Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum Directions {
     UP,
     DOWN,
@@ -16,11 +35,229 @@ pub enum Directions {
     RIGHT,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum Heuristics {
     NONE,
     MANHATTAN,
     LINCONFLICT,
+    // linear conflicts with the corner tiles and last moves enhancements
+    LINCONFLICTPLUS,
+    PATTERNDB,
+    WALKINGDISTANCE,
+}
+
+impl FromStr for Heuristics {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(Heuristics::NONE),
+            "manhattan" => Ok(Heuristics::MANHATTAN),
+            "linconflict" => Ok(Heuristics::LINCONFLICT),
+            "linconflictplus" => Ok(Heuristics::LINCONFLICTPLUS),
+            "patterndb" => Ok(Heuristics::PATTERNDB),
+            "walkingdistance" => Ok(Heuristics::WALKINGDISTANCE),
+            _ => Err(format!("unknown heuristic '{s}'")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    Empty,
+    InvalidSize {
+        line: usize,
+        token: String,
+    },
+    MissingRows {
+        expected: usize,
+        found: usize,
+    },
+    WrongRowWidth {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    InvalidTile {
+        line: usize,
+        column: usize,
+        token: String,
+    },
+    TileOutOfRange {
+        line: usize,
+        column: usize,
+        value: usize,
+    },
+    DuplicateTile {
+        line: usize,
+        column: usize,
+        value: usize,
+    },
+    UnexpectedLine {
+        line: usize,
+    },
+    // the input could not be read any further
+    Read {
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "the puzzle is empty"),
+            ParseError::InvalidSize { line, token } => {
+                write!(f, "line {line}: invalid puzzle size '{token}'")
+            }
+            ParseError::MissingRows { expected, found } => {
+                write!(f, "expected {expected} rows, found {found}")
+            }
+            ParseError::WrongRowWidth {
+                line,
+                expected,
+                found,
+            } => write!(f, "line {line}: expected {expected} tiles, found {found}"),
+            ParseError::InvalidTile {
+                line,
+                column,
+                token,
+            } => write!(f, "line {line}, column {column}: invalid tile '{token}'"),
+            ParseError::TileOutOfRange {
+                line,
+                column,
+                value,
+            } => write!(
+                f,
+                "line {line}, column {column}: tile {value} is out of range"
+            ),
+            ParseError::DuplicateTile {
+                line,
+                column,
+                value,
+            } => write!(f, "line {line}, column {column}: duplicate tile {value}"),
+            ParseError::UnexpectedLine { line } => {
+                write!(f, "line {line}: unexpected content after the last row")
+            }
+            ParseError::Read { line, message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Move of a sequence taking the blank out of the board, index being its 0-based position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IllegalMove {
+    pub index: usize,
+    pub direction: Directions,
+}
+
+impl fmt::Display for IllegalMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "move {} ({}) takes the blank out of the board",
+            self.index, self.direction
+        )
+    }
+}
+
+impl std::error::Error for IllegalMove {}
+
+const MAX_RANKED_CELLS: usize = 20;
+
+// Longest line whose linear conflicts are counted without allocating
+const MAX_LINE_LENGTH: usize = 64;
+
+// "n" or "rows cols", rejected when the number of cells overflows
+fn parse_size(line: usize, size_str: &str) -> Result<(usize, usize), ParseError> {
+    let size_str = size_str.trim();
+    let invalid_size = || ParseError::InvalidSize {
+        line,
+        token: size_str.to_string(),
+    };
+    let dims = size_str
+        .split_whitespace()
+        .map(|token| token.parse::<usize>().ok().filter(|&d| d > 0))
+        .collect::<Option<Vec<usize>>>()
+        .ok_or_else(invalid_size)?;
+    let (rows, cols) = match dims[..] {
+        [n] => (n, n),
+        [rows, cols] => (rows, cols),
+        _ => return Err(invalid_size()),
+    };
+    rows.checked_mul(cols).ok_or_else(invalid_size)?;
+    Ok((rows, cols))
+}
+
+// Splits a line on whitespace, keeping the 1-based column of each token
+fn tokens(line: &str) -> impl Iterator<Item = (usize, &str)> {
+    line.split_whitespace()
+        .map(move |token| (token.as_ptr() as usize - line.as_ptr() as usize + 1, token))
+}
+
+// A tile of a puzzle with seen.len() cells, marked as seen
+fn parse_tile(
+    line: usize,
+    column: usize,
+    token: &str,
+    seen: &mut [bool],
+) -> Result<usize, ParseError> {
+    let value: usize = token.parse().map_err(|_| ParseError::InvalidTile {
+        line,
+        column,
+        token: token.to_string(),
+    })?;
+    check_tile(line, column, value, seen)
+}
+
+fn check_tile(
+    line: usize,
+    column: usize,
+    value: usize,
+    seen: &mut [bool],
+) -> Result<usize, ParseError> {
+    if value >= seen.len() {
+        return Err(ParseError::TileOutOfRange {
+            line,
+            column,
+            value,
+        });
+    }
+    if seen[value] {
+        return Err(ParseError::DuplicateTile {
+            line,
+            column,
+            value,
+        });
+    }
+    seen[value] = true;
+    Ok(value)
+}
+
+impl Directions {
+    pub const ALL: [Directions; 4] = [
+        Directions::UP,
+        Directions::DOWN,
+        Directions::LEFT,
+        Directions::RIGHT,
+    ];
+
+    // The move cancelling this one
+    pub fn opposite(self) -> Self {
+        match self {
+            Directions::UP => Directions::DOWN,
+            Directions::DOWN => Directions::UP,
+            Directions::LEFT => Directions::RIGHT,
+            Directions::RIGHT => Directions::LEFT,
+        }
+    }
 }
 
 impl fmt::Display for Directions {
@@ -34,85 +271,585 @@ impl fmt::Display for Directions {
     }
 }
 
-impl Board {
-    pub fn get_n(&self) -> usize {
-        self.n
+// Writes the board as a puzzle file, the tiles right-aligned as in the test puzzles
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.rows == self.cols {
+            writeln!(f, "{}", self.rows)?;
+        } else {
+            writeln!(f, "{} {}", self.rows, self.cols)?;
+        }
+        let width = (self.rows * self.cols - 1).to_string().len();
+        for row in self.grid.iter_rows() {
+            let tiles: Vec<String> = row.map(|tile| format!("{tile:>width$}")).collect();
+            writeln!(f, "{}", tiles.join(" "))?;
+        }
+        Ok(())
     }
+}
 
-    pub fn get_grid(&self) -> &Grid<usize> {
-        &self.grid
+// Parses a puzzle file: the size on the first line ("n" for a square puzzle, or "rows cols"),
+// followed by the rows
+impl FromStr for Board {
+    type Err = ParseError;
+
+    fn from_str(puzzle_str: &str) -> Result<Self, Self::Err> {
+        Board::parse_lines(puzzle_str.lines().enumerate().map(|(i, l)| (i + 1, l)))
     }
+}
 
-    pub fn get_pos_0(&self) -> (usize, usize) {
-        self.pos_0
+// Serialized form of a Board: its size and its rows of tiles
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct BoardRows {
+    rows: usize,
+    cols: usize,
+    tiles: Vec<Vec<usize>>,
+}
+
+#[cfg(feature = "serde")]
+impl From<Board> for BoardRows {
+    fn from(board: Board) -> Self {
+        BoardRows {
+            rows: board.rows,
+            cols: board.cols,
+            tiles: board
+                .grid
+                .iter_rows()
+                .map(|row| row.copied().collect())
+                .collect(),
+        }
+    }
+}
+
+// Checked like a puzzle file, the rows and columns being numbered from 1
+#[cfg(feature = "serde")]
+impl TryFrom<BoardRows> for Board {
+    type Error = ParseError;
+
+    fn try_from(data: BoardRows) -> Result<Self, Self::Error> {
+        let (rows, cols) = (data.rows, data.cols);
+        if rows == 0 || cols == 0 {
+            return Err(ParseError::Empty);
+        }
+        if data.tiles.len() != rows {
+            return Err(ParseError::MissingRows {
+                expected: rows,
+                found: data.tiles.len(),
+            });
+        }
+        // the size is only trusted once the rows really hold that many tiles
+        if let Some((row, row_tiles)) = data
+            .tiles
+            .iter()
+            .enumerate()
+            .find(|(_, row_tiles)| row_tiles.len() != cols)
+        {
+            return Err(ParseError::WrongRowWidth {
+                line: row + 1,
+                expected: cols,
+                found: row_tiles.len(),
+            });
+        }
+        let cells = rows
+            .checked_mul(cols)
+            .ok_or_else(|| ParseError::InvalidSize {
+                line: 0,
+                token: format!("{rows} {cols}"),
+            })?;
+        let mut seen = vec![false; cells];
+        let mut tiles = Vec::with_capacity(cells);
+        for (row, row_tiles) in data.tiles.iter().enumerate() {
+            for (col, &value) in row_tiles.iter().enumerate() {
+                tiles.push(check_tile(row + 1, col + 1, value, &mut seen)?);
+            }
+        }
+        let blank = tiles.iter().position(|&t| t == 0).unwrap();
+        Ok(Board::new(
+            Grid::from_vec(tiles, cols),
+            (blank / cols, blank % cols),
+        ))
+    }
+}
+
+impl TryFrom<&str> for Board {
+    type Error = ParseError;
+
+    fn try_from(puzzle_str: &str) -> Result<Self, Self::Error> {
+        puzzle_str.parse()
     }
+}
+
+// Read access to the tiles of a puzzle, and the moves of the blank.
+// Everything else (move generation, heuristics, solvability) is shared by every representation.
+pub trait PuzzleState: Clone + Eq + Hash {
+    fn get_rows(&self) -> usize;
+
+    fn get_cols(&self) -> usize;
+
+    fn tile(&self, row: usize, col: usize) -> usize;
+
+    fn get_pos_0(&self) -> (usize, usize);
 
-    pub fn new(board: Grid<usize>, n: usize, pos_0: (usize, usize)) -> Self {
+    fn make_move(&mut self, move_d: Directions);
+
+    // Bytes allocated outside of the state itself, to estimate the memory used by a search
+    fn heap_size(&self) -> usize {
+        0
+    }
+
+    // Index of the state among the (rows x cols)! permutations of its tiles, None if it has
+    // more than 20 cells (21! doesn't fit in 64 bits)
+    fn rank(&self) -> Option<usize> {
+        let (rows, cols) = (self.get_rows(), self.get_cols());
+        if rows * cols > MAX_RANKED_CELLS {
+            return None;
+        }
+        let mut tiles = [0u8; MAX_RANKED_CELLS];
+        for (cell, tile) in tiles[..rows * cols].iter_mut().enumerate() {
+            *tile = self.tile(cell / cols, cell % cols) as u8;
+        }
+        Some(ranking::rank(&tiles[..rows * cols], rows * cols))
+    }
+
+    fn can_move(&self, move_d: Directions) -> bool {
+        let pos_0 = self.get_pos_0();
+        match move_d {
+            Directions::UP => pos_0.0 > 0,
+            Directions::DOWN => pos_0.0 < self.get_rows() - 1,
+            Directions::LEFT => pos_0.1 > 0,
+            Directions::RIGHT => pos_0.1 < self.get_cols() - 1,
+        }
+    }
+
+    fn next_directions(&self) -> Vec<Directions> {
+        Directions::ALL
+            .into_iter()
+            .filter(|&dir| self.can_move(dir))
+            .collect()
+    }
+
+    fn heuristic(&self, heuristic_type: Heuristics, goal: &Goal) -> usize {
+        Evaluator::new(heuristic_type, goal).estimate(self).value
+    }
+
+    // Every move swaps the blank with a tile: it flips the parity of the permutation between the
+    // board and the goal, and moves the blank one cell closer to or further from its goal cell,
+    // so both parities must be equal for the goal to be reachable. They are only enough with at
+    // least two rows and two columns: in a single line the tiles can't pass each other.
+    fn is_solvable(&self, goal: &Goal) -> bool {
+        if goal.get_size() != (self.get_rows(), self.get_cols()) {
+            return false;
+        }
+        if self.get_rows() == 1 || self.get_cols() == 1 {
+            return same_line_order(self, goal);
+        }
+        let (row, col) = self.get_pos_0();
+        let (goal_row, goal_col) = goal.position(0);
+        let blank_distance = row.abs_diff(goal_row) + col.abs_diff(goal_col);
+        permutation_parity(self, goal) == blank_distance % 2
+    }
+}
+
+impl Board {
+    pub fn get_grid(&self) -> &Grid<usize> {
+        &self.grid
+    }
+
+    pub fn new(board: Grid<usize>, pos_0: (usize, usize)) -> Self {
         Board {
+            rows: board.rows(),
+            cols: board.cols(),
             grid: board,
-            n,
             pos_0,
         }
     }
 
-    pub fn heuristic(&self, heuristic_type: Heuristics) -> usize {
-        match heuristic_type {
-            Heuristics::NONE => 0,
-            Heuristics::MANHATTAN => self.heuristic_manhattan(),
-            Heuristics::LINCONFLICT => self.heuristic_linear_conflict(),
+    // Tiles in row-major order with the blank in the bottom-right corner
+    pub fn solved(rows: usize, cols: usize) -> Self {
+        let mut target_vec = Vec::from_iter(1..rows * cols);
+        target_vec.push(0);
+        Board::new(Grid::from_vec(target_vec, cols), (rows - 1, cols - 1))
+    }
+
+    // Copies any representation of a puzzle back into a Board
+    pub fn from_state(state: &impl PuzzleState) -> Self {
+        let (rows, cols) = (state.get_rows(), state.get_cols());
+        let mut tiles = Vec::with_capacity(rows * cols);
+        for row in 0..rows {
+            for col in 0..cols {
+                tiles.push(state.tile(row, col));
+            }
+        }
+        Board::new(Grid::from_vec(tiles, cols), state.get_pos_0())
+    }
+
+    // Board of this size with this index, None if there is no such index
+    pub fn unrank(rows: usize, cols: usize, index: usize) -> Option<Board> {
+        let cells = rows * cols;
+        if cells == 0
+            || cells > MAX_RANKED_CELLS
+            || index >= ranking::permutations(cells, cells).unwrap()
+        {
+            return None;
         }
+        let tiles = ranking::unrank(index, cells, cells);
+        let blank = tiles.iter().position(|&t| t == 0).unwrap();
+        Some(Board::new(
+            Grid::from_vec(tiles, cols),
+            (blank / cols, blank % cols),
+        ))
     }
 
-    pub fn load_from_str(n: usize, puzzle_str: &str) -> Board {
+    // Index of the cells of these tiles (the blank being tile 0), between 0 and
+    // ranking::permutations(rows x cols, tiles.len()), None if the board has more than 64 cells
+    // or the number of placements of the tiles doesn't fit in a usize
+    pub fn rank_tiles(&self, tiles: &[usize]) -> Option<usize> {
+        let cells = self.rows * self.cols;
+        if cells > ranking::MAX_VALUES || ranking::permutations(cells, tiles.len()).is_none() {
+            return None;
+        }
+        let mut cells = vec![0; self.rows * self.cols];
+        for (cell, &tile) in self.grid.iter().enumerate() {
+            cells[tile] = cell;
+        }
+        let positions: Vec<usize> = tiles.iter().map(|&tile| cells[tile]).collect();
+        Some(ranking::rank(&positions, self.rows * self.cols))
+    }
+
+    // Board after every move of the sequence, if they are all possible
+    pub fn apply_moves(&self, moves: &[Directions]) -> Result<Board, IllegalMove> {
+        let mut board = self.clone();
+        for (index, &direction) in moves.iter().enumerate() {
+            if !board.can_move(direction) {
+                return Err(IllegalMove { index, direction });
+            }
+            board.make_move(direction);
+        }
+        Ok(board)
+    }
+
+    // Parses the rows of a puzzle, without the size line
+    pub fn load_from_str(rows: usize, cols: usize, puzzle_str: &str) -> Result<Board, ParseError> {
+        Board::parse_rows(
+            rows,
+            cols,
+            puzzle_str.lines().enumerate().map(|(i, l)| (i + 1, l)),
+        )
+    }
+
+    // Puzzle file given as numbered lines: the size on the first non-blank one, then the rows
+    pub(crate) fn parse_lines<'a>(
+        lines: impl Iterator<Item = (usize, &'a str)>,
+    ) -> Result<Board, ParseError> {
+        let mut lines = lines.skip_while(|(_, l)| l.trim().is_empty());
+        let (line, size_str) = lines.next().ok_or(ParseError::Empty)?;
+        let (rows, cols) = parse_size(line, size_str)?;
+        Board::parse_rows(rows, cols, lines)
+    }
+
+    // Puzzle on a single line: the size, a colon, then every tile in row-major order
+    pub(crate) fn parse_line(line: usize, puzzle_str: &str) -> Result<Board, ParseError> {
+        let (size_str, tiles_str) = puzzle_str.split_once(':').ok_or(ParseError::Empty)?;
+        let (rows, cols) = parse_size(line, size_str)?;
+        let cells = rows * cols;
+        // columns are counted from the start of the line
+        let offset = size_str.len() + 1;
+        let tokens: Vec<(usize, &str)> = tokens(tiles_str)
+            .map(|(column, token)| (column + offset, token))
+            .collect();
+        if tokens.len() != cells {
+            return Err(ParseError::WrongRowWidth {
+                line,
+                expected: cells,
+                found: tokens.len(),
+            });
+        }
+        let mut seen = vec![false; cells];
+        let tiles = tokens
+            .into_iter()
+            .map(|(column, token)| parse_tile(line, column, token, &mut seen))
+            .collect::<Result<Vec<usize>, ParseError>>()?;
+        let blank = tiles.iter().position(|&t| t == 0).unwrap();
+        Ok(Board::new(
+            Grid::from_vec(tiles, cols),
+            (blank / cols, blank % cols),
+        ))
+    }
+
+    fn parse_rows<'a>(
+        rows: usize,
+        cols: usize,
+        mut lines: impl Iterator<Item = (usize, &'a str)>,
+    ) -> Result<Board, ParseError> {
+        if rows == 0 || cols == 0 {
+            return Err(ParseError::Empty);
+        }
+        // nothing is sized from the header before the rows are actually there, the tiles are
+        // checked once they have all been read
         let mut puzzle_vec = vec![];
-        let mut rows = puzzle_str.splitn(n.into(), "\n");
-        let mut p_0 = (n, n);
-        for i in 0..n {
-            let row: &str = rows.next().expect("Out of rows").trim();
-            for val_str in row.split(' ') {
-                if val_str.is_empty() {
-                    continue;
-                }
-                let val: usize = val_str
-                    .parse()
-                    .expect("Impossible to parse this str to usize");
-                if val == 0 {
-                    let y: usize = i;
-                    let x: usize = puzzle_vec.len() - (n * y);
-                    p_0 = (y, x);
-                }
+        let mut columns = vec![];
+        let mut nb_rows = 0;
+        while nb_rows < rows {
+            let Some((line, row)) = lines.next() else {
+                return Err(ParseError::MissingRows {
+                    expected: rows,
+                    found: nb_rows,
+                });
+            };
+            if row.trim().is_empty() {
+                continue;
+            }
+            let tokens: Vec<(usize, &str)> = tokens(row).collect();
+            if tokens.len() != cols {
+                return Err(ParseError::WrongRowWidth {
+                    line,
+                    expected: cols,
+                    found: tokens.len(),
+                });
+            }
+            for (column, val_str) in tokens {
+                let val: usize = val_str.parse().map_err(|_| ParseError::InvalidTile {
+                    line,
+                    column,
+                    token: val_str.to_string(),
+                })?;
                 puzzle_vec.push(val);
+                columns.push((line, column));
             }
+            nb_rows += 1;
         }
-        if p_0.0 == n {
-            panic!("No 0 found!")
+        let mut seen = vec![false; puzzle_vec.len()];
+        for (&val, &(line, column)) in puzzle_vec.iter().zip(&columns) {
+            check_tile(line, column, val, &mut seen)?;
         }
-        let board = Grid::from_vec(puzzle_vec, n);
-        Board {
-            grid: board,
-            n,
-            pos_0: p_0,
+        // rows x cols distinct values below rows x cols means every tile, including 0, was found
+        if let Some((line, _)) = lines.find(|(_, l)| !l.trim().is_empty()) {
+            return Err(ParseError::UnexpectedLine { line });
         }
+        let blank = puzzle_vec.iter().position(|&t| t == 0).unwrap();
+        Ok(Board {
+            grid: Grid::from_vec(puzzle_vec, cols),
+            rows,
+            cols,
+            pos_0: (blank / cols, blank % cols),
+        })
     }
+}
 
-    pub fn next_directions(&self) -> Vec<Directions> {
-        let mut next_pos = vec![];
-        if self.pos_0.0 > 0 {
-            next_pos.push(Directions::UP);
-        }
-        if self.pos_0.0 < self.n - 1 {
-            next_pos.push(Directions::DOWN);
-        }
-        if self.pos_0.1 > 0 {
-            next_pos.push(Directions::LEFT);
+// Parts of a heuristic kept by an Estimate
+const ESTIMATE_PARTS: usize = 3;
+
+// Value of the heuristic for a state, with the parts of it which are updated on each move
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Estimate {
+    pub value: usize,
+    // sums of the patterns of a pattern database for the state and its reflection, or the
+    // linear conflicts (and the vertical and horizontal walking distance states)
+    parts: [u32; ESTIMATE_PARTS],
+}
+
+// Heuristic of one search. The tables it needs are looked up once when it is created instead
+// of on every node, and every move only updates the parts of the heuristic it changes.
+#[derive(Clone)]
+pub struct Evaluator {
+    heuristic: Heuristics,
+    goal: Goal,
+    // None when the goal has no pattern database, linear conflicts being used instead
+    database: Option<Arc<PatternDatabase>>,
+    // None when the board is too large for the walking distance tables, same fallback
+    walking_distance: Option<WalkingDistance>,
+}
+
+impl Evaluator {
+    pub fn new(heuristic: Heuristics, goal: &Goal) -> Self {
+        let database = match heuristic {
+            Heuristics::PATTERNDB => pattern_db::for_goal(goal),
+            _ => None,
+        };
+        let walking_distance = match heuristic {
+            Heuristics::WALKINGDISTANCE => WalkingDistance::for_goal(goal),
+            _ => None,
+        };
+        Evaluator {
+            heuristic,
+            goal: goal.clone(),
+            database,
+            walking_distance,
         }
-        if self.pos_0.1 < self.n - 1 {
-            next_pos.push(Directions::RIGHT);
+    }
+
+    pub fn get_heuristic(&self) -> Heuristics {
+        self.heuristic
+    }
+
+    pub fn get_goal(&self) -> &Goal {
+        &self.goal
+    }
+
+    // Heuristic of a state computed from scratch
+    pub fn estimate(&self, state: &impl PuzzleState) -> Estimate {
+        let goal = &self.goal;
+        let mut parts = [0; ESTIMATE_PARTS];
+        let value = match (self.heuristic, &self.database) {
+            (Heuristics::NONE, _) => 0,
+            (Heuristics::MANHATTAN, _) => heuristic_manhattan(state, goal),
+            (Heuristics::LINCONFLICT, _) | (Heuristics::PATTERNDB, None) => {
+                heuristic_linear_conflict(state, goal)
+            }
+            (Heuristics::LINCONFLICTPLUS, _) => {
+                let linear_conflict = heuristic_linear_conflict(state, goal);
+                parts[0] = linear_conflict as u32;
+                linear_conflict_plus(state, goal, linear_conflict)
+            }
+            (Heuristics::PATTERNDB, Some(database)) => {
+                let (direct, reflected) = database.distances(state);
+                parts[0] = direct as u32;
+                parts[1] = reflected as u32;
+                direct.max(reflected)
+            }
+            // both are admissible, so their maximum is too
+            (Heuristics::WALKINGDISTANCE, _) => {
+                let linear_conflict = heuristic_linear_conflict(state, goal);
+                parts[0] = linear_conflict as u32;
+                match &self.walking_distance {
+                    Some(walking_distance) => {
+                        let states = walking_distance.states(state);
+                        (parts[1], parts[2]) = states;
+                        linear_conflict.max(walking_distance.distance(states))
+                    }
+                    None => linear_conflict,
+                }
+            }
+        };
+        Estimate { value, parts }
+    }
+
+    // Makes the move and returns the heuristic of the new state, from the one before it: this
+    // is how the heuristic is followed along a path, starting from estimate.
+    // Manhattan distance only depends on the moved tile, linear conflicts only on the two lines
+    // it leaves and enters, pattern databases only on the pattern holding it, and walking
+    // distance only on the state of the axis of the move, so they are not recomputed over the
+    // whole board.
+    pub fn make_move<S: PuzzleState>(
+        &self,
+        state: &mut S,
+        move_d: Directions,
+        estimate: Estimate,
+    ) -> Estimate {
+        let goal = &self.goal;
+        let (row, col) = state.get_pos_0();
+        let from = match move_d {
+            Directions::UP => (row - 1, col),
+            Directions::DOWN => (row + 1, col),
+            Directions::LEFT => (row, col - 1),
+            Directions::RIGHT => (row, col + 1),
+        };
+        let to = (row, col);
+        let value = state.tile(from.0, from.1);
+        let manhattan_delta =
+            tile_manhattan(goal, value, to) as isize - tile_manhattan(goal, value, from) as isize;
+        let delta = match (self.heuristic, &self.database) {
+            (Heuristics::NONE, _) => {
+                state.make_move(move_d);
+                0
+            }
+            (Heuristics::MANHATTAN, _) => {
+                state.make_move(move_d);
+                manhattan_delta
+            }
+            (Heuristics::LINCONFLICT, _) | (Heuristics::PATTERNDB, None) => {
+                manhattan_delta + self.move_conflicts(state, move_d, from, to)
+            }
+            (Heuristics::PATTERNDB, Some(database)) => {
+                let cols = state.get_cols();
+                let (direct, reflected) =
+                    database.move_deltas(state, value, from.0 * cols + from.1, to.0 * cols + to.1);
+                state.make_move(move_d);
+                let mut parts = estimate.parts;
+                parts[0] = (parts[0] as isize + direct) as u32;
+                parts[1] = (parts[1] as isize + reflected) as u32;
+                return Estimate {
+                    value: parts[0].max(parts[1]) as usize,
+                    parts,
+                };
+            }
+            (Heuristics::WALKINGDISTANCE, _) => {
+                let mut parts = estimate.parts;
+                let linear_conflict = parts[0] as isize
+                    + manhattan_delta
+                    + self.move_conflicts(state, move_d, from, to);
+                parts[0] = linear_conflict as u32;
+                let Some(walking_distance) = &self.walking_distance else {
+                    return Estimate {
+                        value: linear_conflict as usize,
+                        parts,
+                    };
+                };
+                let states = walking_distance.make_move((parts[1], parts[2]), move_d, value);
+                (parts[1], parts[2]) = states;
+                return Estimate {
+                    value: (linear_conflict as usize).max(walking_distance.distance(states)),
+                    parts,
+                };
+            }
+            // only the enhancements are computed again
+            (Heuristics::LINCONFLICTPLUS, _) => {
+                let mut parts = estimate.parts;
+                let linear_conflict = parts[0] as isize
+                    + manhattan_delta
+                    + self.move_conflicts(state, move_d, from, to);
+                parts[0] = linear_conflict as u32;
+                return Estimate {
+                    value: linear_conflict_plus(state, goal, linear_conflict as usize),
+                    parts,
+                };
+            }
+        };
+        Estimate {
+            value: (estimate.value as isize + delta) as usize,
+            parts: estimate.parts,
         }
-        return next_pos;
     }
 
-    pub fn make_move(&mut self, move_d: Directions) {
+    // Makes the move of the tile from `from` to `to` and returns how much it changed the
+    // conflicts of the lines it crosses
+    fn move_conflicts(
+        &self,
+        state: &mut impl PuzzleState,
+        move_d: Directions,
+        from: (usize, usize),
+        to: (usize, usize),
+    ) -> isize {
+        let before = moved_tile_conflicts(state, &self.goal, from, to) as isize;
+        state.make_move(move_d);
+        let after = moved_tile_conflicts(state, &self.goal, from, to) as isize;
+        after - before
+    }
+}
+
+impl PuzzleState for Board {
+    fn get_rows(&self) -> usize {
+        self.rows
+    }
+
+    fn get_cols(&self) -> usize {
+        self.cols
+    }
+
+    fn tile(&self, row: usize, col: usize) -> usize {
+        self.grid[(row, col)]
+    }
+
+    fn get_pos_0(&self) -> (usize, usize) {
+        self.pos_0
+    }
+
+    fn heap_size(&self) -> usize {
+        self.rows * self.cols * size_of::<usize>()
+    }
+
+    fn make_move(&mut self, move_d: Directions) {
         let x = self.pos_0.0;
         let y = self.pos_0.1;
         match move_d {
@@ -138,122 +875,263 @@ impl Board {
             }
         }
     }
+}
 
-    fn heuristic_manhattan(&self) -> usize {
-        let mut manhattan: usize = 0;
-        for j in 0..self.n {
-            for i in 0..self.n {
-                let val = self.grid[(j, i)];
-                if val != 0 {
-                    let x = (val - 1) % self.n;
-                    let y = (val - 1) / self.n;
-                    let dx: i16 = i16::abs(i16::try_from(x).unwrap() - i16::try_from(i).unwrap());
-                    let dy: i16 = i16::abs(i16::try_from(y).unwrap() - i16::try_from(j).unwrap());
-                    manhattan += usize::try_from(dx + dy).unwrap();
-                }
+// Whether the tiles of a single row or column come in the same order as in the goal, whatever
+// the position of the blank
+fn same_line_order(state: &impl PuzzleState, goal: &Goal) -> bool {
+    let (rows, cols) = (state.get_rows(), state.get_cols());
+    let cells = || (0..rows * cols).map(|cell| (cell / cols, cell % cols));
+    let tiles = cells().map(|(row, col)| state.tile(row, col));
+    let goal_tiles = cells().map(|(row, col)| goal.get_board().tile(row, col));
+    tiles
+        .filter(|&tile| tile != 0)
+        .eq(goal_tiles.filter(|&tile| tile != 0))
+}
+
+// Parity of the permutation sending every cell of the board to the goal cell of its tile
+fn permutation_parity(state: &impl PuzzleState, goal: &Goal) -> usize {
+    let (rows, cols) = (state.get_rows(), state.get_cols());
+    let mut destination = Vec::with_capacity(rows * cols);
+    for row in 0..rows {
+        for col in 0..cols {
+            let (goal_row, goal_col) = goal.position(state.tile(row, col));
+            destination.push(goal_row * cols + goal_col);
+        }
+    }
+    // a cycle of length k is made of k - 1 transpositions
+    let mut visited = vec![false; rows * cols];
+    let mut transpositions = 0;
+    for start in 0..rows * cols {
+        let mut cell = start;
+        let mut length = 0;
+        while !visited[cell] {
+            visited[cell] = true;
+            cell = destination[cell];
+            length += 1;
+        }
+        transpositions += length.max(1) - 1;
+    }
+    transpositions % 2
+}
+
+fn heuristic_manhattan(state: &impl PuzzleState, goal: &Goal) -> usize {
+    let mut manhattan: usize = 0;
+    for j in 0..state.get_rows() {
+        for i in 0..state.get_cols() {
+            let val = state.tile(j, i);
+            if val != 0 {
+                manhattan += tile_manhattan(goal, val, (j, i));
             }
         }
-        return manhattan;
     }
-    fn heuristic_linear_conflict(&self) -> usize {
-        // Commencer avec la distance Manhattan
-        let mut total = self.heuristic_manhattan();
+    manhattan
+}
 
-        // Ajouter les conflits linéaires pour les lignes
-        total += self.count_row_conflicts();
+fn heuristic_linear_conflict(state: &impl PuzzleState, goal: &Goal) -> usize {
+    // Commencer avec la distance Manhattan
+    let mut total = heuristic_manhattan(state, goal);
 
-        // Ajouter les conflits linéaires pour les colonnes
-        total += self.count_col_conflicts();
+    // Ajouter les conflits linéaires pour les lignes
+    total += count_row_conflicts(state, goal);
 
-        total
-    }
+    // Ajouter les conflits linéaires pour les colonnes
+    total += count_col_conflicts(state, goal);
 
-    fn count_row_conflicts(&self) -> usize {
-        let mut conflicts = 0;
+    total
+}
 
-        for row in 0..self.n {
-            // Collecter les tuiles qui appartiennent à cette ligne
-            let mut tiles_in_correct_row = Vec::new();
+fn count_row_conflicts(state: &impl PuzzleState, goal: &Goal) -> usize {
+    (0..state.get_rows())
+        .map(|row| row_conflicts(state, goal, row))
+        .sum()
+}
 
-            for col in 0..self.n {
-                let value = self.grid[(row, col)];
-                if value != 0 {
-                    // Calculer la ligne cible de cette tuile
-                    let target_row = (value - 1) / self.n;
+fn row_conflicts(state: &impl PuzzleState, goal: &Goal, row: usize) -> usize {
+    // Cibles des tuiles qui appartiennent à cette ligne, de gauche à droite
+    let targets = (0..state.get_cols()).filter_map(|col| {
+        let value = state.tile(row, col);
+        let (target_row, target_col) = goal.position(value);
+        (value != 0 && target_row == row).then_some(target_col)
+    });
+    count_conflicts_in_line(targets, state.get_cols())
+}
 
-                    // Si la tuile est sur la bonne ligne
-                    if target_row == row {
-                        let target_col = (value - 1) % self.n;
-                        tiles_in_correct_row.push((col, target_col));
-                    }
-                }
-            }
+fn count_col_conflicts(state: &impl PuzzleState, goal: &Goal) -> usize {
+    (0..state.get_cols())
+        .map(|col| col_conflicts(state, goal, col))
+        .sum()
+}
 
-            // Compter les conflits dans cette ligne
-            conflicts += self.count_conflicts_in_line(&tiles_in_correct_row);
-        }
+fn col_conflicts(state: &impl PuzzleState, goal: &Goal, col: usize) -> usize {
+    // Cibles des tuiles qui appartiennent à cette colonne, de haut en bas
+    let targets = (0..state.get_rows()).filter_map(|row| {
+        let value = state.tile(row, col);
+        let (target_row, target_col) = goal.position(value);
+        (value != 0 && target_col == col).then_some(target_row)
+    });
+    count_conflicts_in_line(targets, state.get_rows())
+}
 
-        conflicts
+// Conflicts of the two lines crossed by a tile moving from `from` to `to`.
+// A vertical move keeps the order of the tiles of its column, so only the two rows change,
+// and the other way around for a horizontal move.
+fn moved_tile_conflicts(
+    state: &impl PuzzleState,
+    goal: &Goal,
+    from: (usize, usize),
+    to: (usize, usize),
+) -> usize {
+    if from.0 != to.0 {
+        row_conflicts(state, goal, from.0) + row_conflicts(state, goal, to.0)
+    } else {
+        col_conflicts(state, goal, from.1) + col_conflicts(state, goal, to.1)
     }
+}
 
-    fn count_col_conflicts(&self) -> usize {
-        let mut conflicts = 0;
-
-        for col in 0..self.n {
-            // Collecter les tuiles qui appartiennent à cette colonne
-            let mut tiles_in_correct_col = Vec::new();
-
-            for row in 0..self.n {
-                let value = self.grid[(row, col)];
-                if value != 0 {
-                    // Calculer la colonne cible de cette tuile
-                    let target_col = (value - 1) % self.n;
+fn tile_manhattan(goal: &Goal, value: usize, (row, col): (usize, usize)) -> usize {
+    let (y, x) = goal.position(value);
+    x.abs_diff(col) + y.abs_diff(row)
+}
 
-                    // Si la tuile est sur la bonne colonne
-                    if target_col == col {
-                        let target_row = (value - 1) / self.n;
-                        tiles_in_correct_col.push((row, target_row));
-                    }
-                }
-            }
+// Each tile of the line which has to leave it to let others by costs two more moves than its
+// Manhattan distance. The tiles which stay keep their order, so at least every tile outside
+// the longest sequence of tiles already in the order of their targets has to leave.
+// The targets are given in the order of the tiles along the line, of this length.
+fn count_conflicts_in_line(targets: impl Iterator<Item = usize>, length: usize) -> usize {
+    if length > MAX_LINE_LENGTH {
+        return count_conflicts_in_long_line(targets);
+    }
+    // smallest last target of the increasing sequences of every length, as a set of targets
+    let mut tails: u64 = 0;
+    let mut tiles = 0;
+    for target in targets {
+        // the target replaces the first tail above it, or makes a longer sequence
+        let above = tails & !((1 << target) - 1);
+        tails = (tails & !(above & above.wrapping_neg())) | 1 << target;
+        tiles += 1;
+    }
+    2 * (tiles - tails.count_ones() as usize)
+}
 
-            // Compter les conflits dans cette colonne
-            conflicts += self.count_conflicts_in_line(&tiles_in_correct_col);
+// Same as count_conflicts_in_line, for lines too long for the targets to be the bits of a u64
+fn count_conflicts_in_long_line(targets: impl Iterator<Item = usize>) -> usize {
+    let mut tails: Vec<usize> = Vec::new();
+    let mut tiles = 0;
+    for target in targets {
+        let length = tails.partition_point(|&tail| tail < target);
+        if length == tails.len() {
+            tails.push(target);
+        } else {
+            tails[length] = target;
         }
-
-        conflicts
+        tiles += 1;
     }
+    2 * (tiles - tails.len())
+}
 
-    fn count_conflicts_in_line(&self, tiles: &[(usize, usize)]) -> usize {
-        let mut conflicts = 0;
-        let n = tiles.len();
+// Whether the tile at this position is in its goal row or column, in the wrong order with
+// another tile of that line
+fn in_conflict(state: &impl PuzzleState, goal: &Goal, (row, col): (usize, usize)) -> bool {
+    let (target_row, target_col) = goal.position(state.tile(row, col));
+    let row_conflict = target_row == row
+        && (0..state.get_cols()).any(|other| {
+            let value = state.tile(row, other);
+            let (other_row, other_col) = goal.position(value);
+            value != 0 && other_row == row && (other < col) != (other_col < target_col)
+        });
+    let col_conflict = target_col == col
+        && (0..state.get_rows()).any(|other| {
+            let value = state.tile(other, col);
+            let (other_row, other_col) = goal.position(value);
+            value != 0 && other_col == col && (other < row) != (other_row < target_row)
+        });
+    row_conflict || col_conflict
+}
 
-        for i in 0..n {
-            for j in (i + 1)..n {
-                let (pos1, target1) = tiles[i];
-                let (pos2, target2) = tiles[j];
+// Linear conflicts of the state, plus the extra moves found by the two enhancements below.
+// They only use tiles in no linear conflict, which are never among the ones counted as leaving
+// their line, and never the same tile twice. The linear conflicts are given, as the searches
+// update them on each move.
+fn linear_conflict_plus(state: &impl PuzzleState, goal: &Goal, linear_conflict: usize) -> usize {
+    if linear_conflict == 0 {
+        return 0;
+    }
+    // the two tiles next to every corner counted as leaving their cell, the unused slots
+    // holding the blank which is never one of them
+    let mut moved = [0; 8];
+    let corners = corner_tiles(state, goal, &mut moved);
+    linear_conflict + corners + last_moves(state, goal, &moved)
+}
 
-                // Conflit si les tuiles sont dans le mauvais ordre relatif
-                // par rapport à leurs positions cibles
-                if (pos1 < pos2 && target1 > target2) || (pos1 > pos2 && target1 < target2) {
-                    conflicts += 2; // Chaque conflit coûte 2 mouvements supplémentaires
-                }
+// When a corner doesn't hold its tile, the blank or that tile has to go through one of the two
+// cells next to it. If both hold their own tile, one of them leaves it and comes back.
+fn corner_tiles(state: &impl PuzzleState, goal: &Goal, moved: &mut [usize; 8]) -> usize {
+    let (rows, cols) = (state.get_rows(), state.get_cols());
+    if rows < 2 || cols < 2 {
+        return 0;
+    }
+    let goal_board = goal.get_board();
+    let mut extra = 0;
+    let corners = [(0, 0), (0, cols - 1), (rows - 1, 0), (rows - 1, cols - 1)];
+    for (i, (row, col)) in corners.into_iter().enumerate() {
+        let corner_tile = goal_board.tile(row, col);
+        if corner_tile == 0 || state.tile(row, col) == corner_tile {
+            continue;
+        }
+        let neighbors = [
+            (row, if col == 0 { 1 } else { col - 1 }),
+            (if row == 0 { 1 } else { row - 1 }, col),
+        ];
+        let in_place = neighbors.iter().all(|&(r, c)| {
+            let value = goal_board.tile(r, c);
+            value != 0
+                && state.tile(r, c) == value
+                && !moved.contains(&value)
+                && !in_conflict(state, goal, (r, c))
+        });
+        if in_place {
+            extra += 2;
+            for (slot, &(r, c)) in neighbors.iter().enumerate() {
+                moved[2 * i + slot] = goal_board.tile(r, c);
             }
         }
-
-        conflicts
     }
+    extra
 }
 
-impl PartialEq for Board {
-    fn eq(&self, other: &Self) -> bool {
-        return &self.grid == &other.grid;
-    }
+// The last move brings one of the tiles next to the goal position of the blank from that
+// position. If going there is a detour for all of them, one of them makes two more moves.
+fn last_moves(state: &impl PuzzleState, goal: &Goal, moved: &[usize]) -> usize {
+    let (rows, cols) = (state.get_rows(), state.get_cols());
+    let (blank_row, blank_col) = goal.position(0);
+    let neighbors = [
+        blank_row.checked_sub(1).map(|row| (row, blank_col)),
+        (blank_row + 1 < rows).then_some((blank_row + 1, blank_col)),
+        blank_col.checked_sub(1).map(|col| (blank_row, col)),
+        (blank_col + 1 < cols).then_some((blank_row, blank_col + 1)),
+    ];
+    let detours = neighbors.into_iter().flatten().all(|(row, col)| {
+        let value = goal.get_board().tile(row, col);
+        if moved.contains(&value) {
+            return false;
+        }
+        let cell = (0..rows * cols)
+            .find(|&cell| state.tile(cell / cols, cell % cols) == value)
+            .unwrap();
+        let (y, x) = (cell / cols, cell % cols);
+        let to_blank = y.abs_diff(blank_row) + x.abs_diff(blank_col);
+        let to_goal = y.abs_diff(row) + x.abs_diff(col);
+        to_blank > to_goal && !in_conflict(state, goal, (y, x))
+    });
+    if detours { 2 } else { 0 }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::state_space::StateSpace;
+    use std::fs;
 
     #[test]
     fn load_from_str_test() {
@@ -265,12 +1143,128 @@ mod tests {
         */
         let n = 3;
         let input_str = "0 1 3\n4 2 5\n7 8 6";
-        let board = Board::load_from_str(n, input_str);
+        let board = Board::load_from_str(n, n, input_str).unwrap();
 
-        assert_eq!(board.n, 3);
+        assert_eq!((board.rows, board.cols), (3, 3));
         assert_eq!(board.pos_0, (0, 0));
     }
 
+    #[test]
+    fn from_str_test() {
+        let board: Board = "3\n 0 1 3\n 4 2 5\n 7 8 6\n\n".parse().unwrap();
+        assert_eq!((board.rows, board.cols), (3, 3));
+        assert_eq!(board.pos_0, (0, 0));
+        assert_eq!(Board::try_from("3\n0 1 3\n4 2 5\n7 8 6"), Ok(board.clone()));
+        assert_eq!("3 3\n0 1 3\n4 2 5\n7 8 6".parse(), Ok(board));
+
+        /*
+        2 4
+        1 2 3 4
+        5 0 6 7
+        */
+        let board: Board = "2 4\n1 2 3 4\n5 0 6 7".parse().unwrap();
+        assert_eq!((board.rows, board.cols), (2, 4));
+        assert_eq!(board.pos_0, (1, 1));
+        assert_eq!(
+            board.next_directions(),
+            vec![Directions::UP, Directions::LEFT, Directions::RIGHT]
+        );
+    }
+
+    #[test]
+    fn from_str_errors_test() {
+        assert_eq!("".parse::<Board>(), Err(ParseError::Empty));
+        assert_eq!(
+            "three\n0 1 3\n4 2 5\n7 8 6".parse::<Board>(),
+            Err(ParseError::InvalidSize {
+                line: 1,
+                token: "three".to_string()
+            })
+        );
+        assert_eq!(
+            "2 3 4\n0 1 3\n4 2 5".parse::<Board>(),
+            Err(ParseError::InvalidSize {
+                line: 1,
+                token: "2 3 4".to_string()
+            })
+        );
+        assert_eq!(
+            "2 3\n0 1 3 4\n2 5 6".parse::<Board>(),
+            Err(ParseError::WrongRowWidth {
+                line: 2,
+                expected: 3,
+                found: 4
+            })
+        );
+        assert_eq!(
+            "3\n0 1 3\n4 2 5".parse::<Board>(),
+            Err(ParseError::MissingRows {
+                expected: 3,
+                found: 2
+            })
+        );
+        assert_eq!(
+            "3\n0 1 3\n4 2\n7 8 6 5".parse::<Board>(),
+            Err(ParseError::WrongRowWidth {
+                line: 3,
+                expected: 3,
+                found: 2
+            })
+        );
+        assert_eq!(
+            "3\n0 1 3\n4 x 5\n7 8 6".parse::<Board>(),
+            Err(ParseError::InvalidTile {
+                line: 3,
+                column: 3,
+                token: "x".to_string()
+            })
+        );
+        assert_eq!(
+            "3\n0 1 3\n4 2 5\n7 8 9".parse::<Board>(),
+            Err(ParseError::TileOutOfRange {
+                line: 4,
+                column: 5,
+                value: 9
+            })
+        );
+        assert_eq!(
+            "3\n0 1 3\n4 2 5\n7 1 6".parse::<Board>(),
+            Err(ParseError::DuplicateTile {
+                line: 4,
+                column: 3,
+                value: 1
+            })
+        );
+        assert_eq!(
+            "3\n0 1 3\n4 2 5\n7 8 6\n1".parse::<Board>(),
+            Err(ParseError::UnexpectedLine { line: 5 })
+        );
+
+        // sizes which can't be allocated are rejected, not trusted
+        assert_eq!(
+            "4294967296 4294967296\n0".parse::<Board>(),
+            Err(ParseError::InvalidSize {
+                line: 1,
+                token: "4294967296 4294967296".to_string()
+            })
+        );
+        assert_eq!(
+            "1000000\n0 1".parse::<Board>(),
+            Err(ParseError::WrongRowWidth {
+                line: 2,
+                expected: 1000000,
+                found: 2
+            })
+        );
+        assert_eq!(
+            "1000000 1\n0\n1".parse::<Board>(),
+            Err(ParseError::MissingRows {
+                expected: 1000000,
+                found: 2
+            })
+        );
+    }
+
     #[test]
     fn next_directions_test1() {
         /*
@@ -281,7 +1275,7 @@ mod tests {
         */
         let n = 3;
         let input_str = "0 1 3\n4 2 5\n7 8 6";
-        let board = Board::load_from_str(n, input_str);
+        let board = Board::load_from_str(n, n, input_str).unwrap();
         let next_d = board.next_directions();
 
         assert_eq!(next_d.len(), 2);
@@ -299,7 +1293,7 @@ mod tests {
         */
         let n = 3;
         let input_str = "6 1 3\n4 2 5\n7 8 0";
-        let board = Board::load_from_str(n, input_str);
+        let board = Board::load_from_str(n, n, input_str).unwrap();
         let next_d = board.next_directions();
 
         assert_eq!(next_d.len(), 2);
@@ -317,7 +1311,7 @@ mod tests {
         */
         let n = 3;
         let input_str = "2 1 3\n4 0 5\n7 8 6";
-        let board = Board::load_from_str(n, input_str);
+        let board = Board::load_from_str(n, n, input_str).unwrap();
         let next_d = board.next_directions();
 
         assert_eq!(next_d.len(), 4);
@@ -337,7 +1331,7 @@ mod tests {
         */
         let n = 3;
         let input_str = "0 1 3\n4 2 5\n7 8 6";
-        let mut board = Board::load_from_str(n, input_str);
+        let mut board = Board::load_from_str(n, n, input_str).unwrap();
         board.make_move(Directions::RIGHT);
 
         assert_eq!(*board.get_grid(), grid![[1, 0, 3] [4, 2, 5] [7, 8, 6]]);
@@ -353,7 +1347,7 @@ mod tests {
         */
         let n = 3;
         let input_str = "6 1 3\n4 2 5\n7 8 0";
-        let mut board = Board::load_from_str(n, input_str);
+        let mut board = Board::load_from_str(n, n, input_str).unwrap();
         board.make_move(Directions::UP);
 
         assert_eq!(*board.get_grid(), grid![[6, 1, 3] [4, 2, 0] [7, 8, 5]]);
@@ -369,9 +1363,9 @@ mod tests {
         */
         let n = 3;
         let input_str = "1 2 3\n4 5 6\n7 8 0";
-        let board = Board::load_from_str(n, input_str);
+        let board = Board::load_from_str(n, n, input_str).unwrap();
 
-        assert_eq!(board.heuristic_manhattan(), 0);
+        assert_eq!(heuristic_manhattan(&board, &Goal::standard(n, n)), 0);
     }
 
     #[test]
@@ -384,9 +1378,9 @@ mod tests {
         */
         let n = 3;
         let input_str = "2 1 3\n4 0 5\n7 8 6";
-        let board = Board::load_from_str(n, input_str);
+        let board = Board::load_from_str(n, n, input_str).unwrap();
 
-        assert_eq!(board.heuristic_manhattan(), 4);
+        assert_eq!(heuristic_manhattan(&board, &Goal::standard(n, n)), 4);
     }
 
     #[test]
@@ -408,16 +1402,381 @@ mod tests {
         */
         let n = 3;
         let input_str = "2 1 3\n4 0 5\n7 8 6";
-        let board = Board::load_from_str(n, input_str);
+        let board = Board::load_from_str(n, n, input_str).unwrap();
 
-        let linear_conflict_result = board.heuristic(Heuristics::LINCONFLICT);
+        let goal = Goal::standard(n, n);
+        let linear_conflict_result = board.heuristic(Heuristics::LINCONFLICT, &goal);
 
         // Vérifier que Linear Conflict >= Manhattan
-        let manhattan_result = board.heuristic(Heuristics::MANHATTAN);
+        let manhattan_result = board.heuristic(Heuristics::MANHATTAN, &goal);
         assert!(linear_conflict_result >= manhattan_result);
 
         // Vérifier le résultat exact
         // Manhattan = 4, Conflits linéaires = 2, Total = 6
         assert_eq!(linear_conflict_result, 6);
     }
+
+    #[test]
+    fn count_conflicts_in_line_test() {
+        let conflicts = |targets: &[usize]| count_conflicts_in_line(targets.iter().copied(), 4);
+        assert_eq!(conflicts(&[]), 0);
+        assert_eq!(conflicts(&[0, 1, 2]), 0);
+        assert_eq!(conflicts(&[1, 0, 2]), 2);
+        // only two of the tiles of a reversed line have to leave it, not one per pair
+        assert_eq!(conflicts(&[2, 1, 0]), 4);
+        assert_eq!(conflicts(&[3, 1, 2, 0]), 4);
+        // lines longer than a u64 have the same conflicts
+        let long = |targets: &[usize]| count_conflicts_in_long_line(targets.iter().copied());
+        assert_eq!(long(&[1, 0, 2]), 2);
+        assert_eq!(long(&[3, 1, 2, 0]), 4);
+        assert_eq!(count_conflicts_in_line((0..64).rev(), 64), 2 * 63);
+        let reversed: Vec<usize> = (0..100).rev().collect();
+        assert_eq!(
+            count_conflicts_in_line(reversed.iter().copied(), 100),
+            2 * 99
+        );
+
+        /*
+        3 2 1
+        4 5 6
+        7 8 0
+        */
+        let board = Board::load_from_str(3, 3, "3 2 1\n4 5 6\n7 8 0").unwrap();
+        let goal = Goal::standard(3, 3);
+        assert_eq!(board.heuristic(Heuristics::MANHATTAN, &goal), 4);
+        assert_eq!(board.heuristic(Heuristics::LINCONFLICT, &goal), 8);
+    }
+
+    #[test]
+    fn heuristic_linear_conflict_plus_test() {
+        let goal = Goal::standard(3, 3);
+        let space = StateSpace::explore(goal.clone()).unwrap();
+        /*
+        5 2 3
+        4 6 1
+        7 8 0
+        the 2 or the 4 has to make way for the 1, and the last move brings the 6 or the 8
+        */
+        let board = Board::load_from_str(3, 3, "5 2 3\n4 6 1\n7 8 0").unwrap();
+        assert_eq!(board.heuristic(Heuristics::LINCONFLICT, &goal), 6);
+        assert_eq!(board.heuristic(Heuristics::LINCONFLICTPLUS, &goal), 10);
+        assert!(space.distance(&board).unwrap() >= 10);
+
+        /*
+        2 1 3
+        4 0 5
+        7 8 6
+        the 1 and the 2 are in conflict, and the 6 is away from the blank
+        */
+        let board = Board::load_from_str(3, 3, "2 1 3\n4 0 5\n7 8 6").unwrap();
+        assert_eq!(board.heuristic(Heuristics::LINCONFLICTPLUS, &goal), 6);
+
+        assert_eq!(
+            Board::solved(3, 3).heuristic(Heuristics::LINCONFLICTPLUS, &goal),
+            0
+        );
+    }
+
+    #[test]
+    fn linear_conflict_admissible_test() {
+        // never above the exact distance of any board small enough to explore
+        for goal in [
+            Goal::standard(3, 3),
+            Goal::snail(3, 3),
+            Goal::blank_first(3, 3),
+            Goal::standard(2, 4),
+            Goal::standard(2, 3),
+        ] {
+            let space = StateSpace::explore(goal.clone()).unwrap();
+            let (rows, cols) = goal.get_size();
+            let mut improved = 0;
+            for index in 0..ranking::permutations(rows * cols, rows * cols).unwrap() {
+                let board = Board::unrank(rows, cols, index).unwrap();
+                let Some(distance) = space.distance(&board) else {
+                    continue;
+                };
+                let linear_conflict = board.heuristic(Heuristics::LINCONFLICT, &goal);
+                let plus = board.heuristic(Heuristics::LINCONFLICTPLUS, &goal);
+                assert!(linear_conflict <= plus && plus <= distance, "{board}");
+                if plus > linear_conflict {
+                    improved += 1;
+                }
+            }
+            assert!(improved > 0);
+        }
+    }
+
+    #[test]
+    fn is_solvable_test1() {
+        /*
+        3
+        0 1 3
+        4 2 5
+        7 8 6
+        */
+        let n = 3;
+        let input_str = "0 1 3\n4 2 5\n7 8 6";
+        let board = Board::load_from_str(n, n, input_str).unwrap();
+
+        assert!(board.is_solvable(&Goal::standard(n, n)));
+    }
+
+    #[test]
+    fn is_solvable_test2() {
+        /*
+        3
+        1 2 3
+        4 5 6
+        8 7 0
+        */
+        let n = 3;
+        let input_str = "1 2 3\n4 5 6\n8 7 0";
+        let board = Board::load_from_str(n, n, input_str).unwrap();
+
+        assert!(!board.is_solvable(&Goal::standard(n, n)));
+    }
+
+    #[test]
+    fn is_solvable_even_test() {
+        /*
+        2
+        1 2
+        0 3
+        The blank moved one row up: no inversion, but still solvable.
+
+        2
+        0 1
+        2 3
+        */
+        let goal = Goal::standard(2, 2);
+        let board = Board::load_from_str(2, 2, "1 2\n0 3").unwrap();
+        assert!(board.is_solvable(&goal));
+
+        let board = Board::load_from_str(2, 2, "1 0\n2 3").unwrap();
+        assert!(!board.is_solvable(&goal));
+    }
+
+    #[test]
+    fn is_solvable_rectangular_test() {
+        /*
+        2 3
+        1 2 3
+        5 4 0
+        Odd width: the inversions alone give the parity, one inversion here.
+
+        3 2
+        1 2
+        0 3
+        4 5
+        Even width: no inversion, but the blank is one row away from its goal row.
+        */
+        let goal = Goal::standard(2, 3);
+        let board = Board::load_from_str(2, 3, "1 2 3\n5 4 0").unwrap();
+        assert!(!board.is_solvable(&goal));
+        let board = Board::load_from_str(2, 3, "4 1 2\n0 5 3").unwrap();
+        assert!(board.is_solvable(&goal));
+
+        let goal = Goal::standard(3, 2);
+        let board = Board::load_from_str(3, 2, "1 2\n3 4\n0 5").unwrap();
+        assert!(board.is_solvable(&goal));
+        let board = Board::load_from_str(3, 2, "1 2\n0 3\n4 5").unwrap();
+        assert!(!board.is_solvable(&goal));
+        assert!(!board.is_solvable(&Goal::standard(2, 3)));
+    }
+
+    #[test]
+    fn is_solvable_line_test() {
+        /*
+        1 4
+        3 1 2 0
+        Even permutation with the blank in place, but the tiles can't pass each other in a
+        single row.
+
+        4 1
+        1
+        0
+        2
+        3
+        */
+        let goal = Goal::standard(1, 4);
+        let board = Board::load_from_str(1, 4, "3 1 2 0").unwrap();
+        assert!(!board.is_solvable(&goal));
+        let board = Board::load_from_str(1, 4, "1 0 2 3").unwrap();
+        assert!(board.is_solvable(&goal));
+
+        let goal = Goal::standard(4, 1);
+        let board = Board::load_from_str(4, 1, "1\n0\n2\n3").unwrap();
+        assert!(board.is_solvable(&goal));
+        let board = Board::load_from_str(4, 1, "2\n0\n1\n3").unwrap();
+        assert!(!board.is_solvable(&goal));
+        assert!(Board::solved(1, 1).is_solvable(&Goal::standard(1, 1)));
+    }
+
+    #[test]
+    fn is_solvable_goal_test() {
+        /*
+        3
+        1 2 3
+        8 0 4
+        7 5 6
+        Two tiles of the snail goal swapped: unsolvable, and solvable once swapped back.
+        */
+        let goal = Goal::snail(3, 3);
+        let board = Board::load_from_str(3, 3, "1 2 3\n8 0 4\n7 5 6").unwrap();
+        assert!(!board.is_solvable(&goal));
+        assert!(goal.get_board().is_solvable(&goal));
+        assert!(!board.is_solvable(&Goal::standard(4, 4)));
+
+        // half of the boards can reach both goals, depending on the goals
+        assert!(!Goal::standard(3, 3).get_board().is_solvable(&goal));
+        assert!(
+            Goal::standard(4, 4)
+                .get_board()
+                .is_solvable(&Goal::snail(4, 4))
+        );
+        assert!(
+            !Goal::standard(4, 4)
+                .get_board()
+                .is_solvable(&Goal::blank_first(4, 4))
+        );
+    }
+
+    #[test]
+    fn evaluator_make_move_test() {
+        // pseudo-random walk, the heuristic updated on each move must match a full computation
+        let mut seed: u64 = 42;
+        for goal in [
+            Goal::standard(3, 3),
+            Goal::standard(4, 4),
+            Goal::snail(4, 4),
+            Goal::standard(2, 5),
+            Goal::snail(4, 3),
+        ] {
+            for heuristic in [
+                Heuristics::NONE,
+                Heuristics::MANHATTAN,
+                Heuristics::LINCONFLICT,
+                Heuristics::LINCONFLICTPLUS,
+                Heuristics::WALKINGDISTANCE,
+            ] {
+                let evaluator = Evaluator::new(heuristic, &goal);
+                let mut board = goal.get_board().clone();
+                let mut estimate = evaluator.estimate(&board);
+                assert_eq!(estimate.value, 0);
+                for _ in 0..500 {
+                    seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                    let directions = board.next_directions();
+                    let dir = directions[(seed >> 33) as usize % directions.len()];
+                    estimate = evaluator.make_move(&mut board, dir, estimate);
+                    assert_eq!(estimate, evaluator.estimate(&board));
+                    assert!(board.is_solvable(&goal));
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_test() {
+        let board: Board = "2 3\n1 2 3\n4 0 5".parse().unwrap();
+        let json = serde_json::to_string(&board).unwrap();
+        assert_eq!(json, r#"{"rows":2,"cols":3,"tiles":[[1,2,3],[4,0,5]]}"#);
+        assert_eq!(serde_json::from_str::<Board>(&json).unwrap(), board);
+
+        let duplicate = r#"{"rows":2,"cols":2,"tiles":[[1,2],[2,0]]}"#;
+        assert!(serde_json::from_str::<Board>(duplicate).is_err());
+        let too_short = r#"{"rows":2,"cols":2,"tiles":[[1,2,3,0]]}"#;
+        assert!(serde_json::from_str::<Board>(too_short).is_err());
+        // nothing is allocated from a size the rows don't match
+        let too_wide = r#"{"rows":1,"cols":1000000000000,"tiles":[[0]]}"#;
+        assert_eq!(
+            Board::try_from(serde_json::from_str::<BoardRows>(too_wide).unwrap()),
+            Err(ParseError::WrongRowWidth {
+                line: 1,
+                expected: 1_000_000_000_000,
+                found: 1
+            })
+        );
+
+        let moves = serde_json::to_string(&[Directions::UP, Directions::LEFT]).unwrap();
+        assert_eq!(moves, r#"["up","left"]"#);
+        assert_eq!(
+            serde_json::from_str::<Heuristics>(r#""patterndb""#).unwrap(),
+            Heuristics::PATTERNDB
+        );
+    }
+
+    #[test]
+    fn apply_moves_test() {
+        /*
+        1 2 3      1 2 3
+        4 0 5  ->  4 5 0
+        */
+        let board: Board = "2 3\n1 2 3\n4 0 5".parse().unwrap();
+        let moved = board.apply_moves(&[Directions::RIGHT]).unwrap();
+        assert_eq!(moved, Board::solved(2, 3));
+        assert_eq!(board.apply_moves(&[]).unwrap(), board);
+        assert_eq!(
+            board.apply_moves(&[Directions::UP, Directions::LEFT, Directions::UP]),
+            Err(IllegalMove {
+                index: 2,
+                direction: Directions::UP
+            })
+        );
+    }
+
+    #[test]
+    fn display_test() {
+        let board: Board = "2 3\n1 2 3\n4 0 5".parse().unwrap();
+        assert_eq!(board.to_string(), "2 3\n1 2 3\n4 0 5\n");
+        let puzzle_str = fs::read_to_string("test_puzzles/puzzle4x4-78.txt").unwrap();
+        let board: Board = puzzle_str.parse().unwrap();
+        assert_eq!(board.to_string(), puzzle_str);
+        assert_eq!(board.to_string().parse(), Ok(board));
+    }
+
+    #[test]
+    fn rank_test() {
+        // every board of 2x2 and 2x3, and a sample of 3x3 and 4x4, comes back from its index
+        for (rows, cols, step) in [(2, 2, 1), (2, 3, 1), (3, 3, 11), (4, 4, 999_999_937)] {
+            let count = ranking::permutations(rows * cols, rows * cols).unwrap();
+            for index in (0..count).step_by(step) {
+                let board = Board::unrank(rows, cols, index).unwrap();
+                assert_eq!(board.rank(), Some(index));
+                assert_eq!(board.tile(board.get_pos_0().0, board.get_pos_0().1), 0);
+            }
+            assert_eq!(Board::unrank(rows, cols, count), None);
+        }
+        // tile t at cell t - 1 has one smaller tile left, the blank: 1! + 2! + ... + 15!
+        let solved_index = (1..16).map(|k| ranking::permutations(k, k).unwrap()).sum();
+        assert_eq!(Board::solved(4, 4).rank(), Some(solved_index));
+        assert_eq!(Board::solved(5, 5).rank(), None);
+
+        let board: Board = "4\n5 4 3 8\n9 2 6 1\n0 13 14 7\n15 11 10 12"
+            .parse()
+            .unwrap();
+        assert_eq!(Board::unrank(4, 4, board.rank().unwrap()), Some(board));
+    }
+
+    #[test]
+    fn rank_tiles_test() {
+        // the placements of 3 tiles of the 2x3 boards are all numbered once
+        let tiles = [0, 4, 5];
+        let mut seen = vec![0; ranking::permutations(6, 3).unwrap()];
+        for index in 0..ranking::permutations(6, 6).unwrap() {
+            let board = Board::unrank(2, 3, index).unwrap();
+            seen[board.rank_tiles(&tiles).unwrap()] += 1;
+        }
+        // 3! boards for every placement
+        assert!(seen.iter().all(|&count| count == 6));
+
+        let board = Board::solved(4, 4);
+        let index = board.rank_tiles(&[1, 2, 0]).unwrap();
+        assert_eq!(ranking::unrank(index, 16, 3), vec![0, 1, 15]);
+        assert_eq!(Board::solved(9, 9).rank_tiles(&[1, 2]), None);
+        // 25! placements don't fit in a usize
+        let tiles: Vec<usize> = (0..25).collect();
+        assert_eq!(Board::solved(5, 5).rank_tiles(&tiles), None);
+        assert!(Board::solved(5, 5).rank_tiles(&tiles[..10]).is_some());
+    }
 }
@@ -0,0 +1,252 @@
+use rust_idastar::{
+    board::Heuristics,
+    solver::{Algo, SearchLimits},
+};
+use std::time::Duration;
+
+pub const USAGE: &str = "Usage: rust-idastar [OPTIONS] [FILE]...
+
+Solves the N-puzzle files given as arguments, or read from stdin if there are none (or with '-').
+A directory stands for every file in it, and a file may hold several puzzles separated by blank
+lines, or written on a single line as \"n: t1 t2 ...\".
+
+Options:
+  -a, --algo <astar|idastar|parallelidastar>  search algorithm [default: astar]
+  -H, --heuristic <none|manhattan|linconflict|linconflictplus|patterndb|walkingdistance>
+                                               heuristic [default: linconflict]
+  -t, --timeout <SECONDS>                      give up on a puzzle after this time
+  -n, --max-nodes <COUNT>                      give up on a puzzle after expanding this many nodes
+  -b, --max-bound <COST>                       give up on a puzzle needing more moves than this
+  -m, --max-memory <MB>                        give up on a puzzle once the search stores about
+                                               this many megabytes of states
+  -g, --goal <standard|blank-first|snail|FILE> goal state, or a puzzle file holding it
+                                               [default: standard]
+  -j, --threads <COUNT>                        threads used by parallelidastar
+                                               [default: number of CPUs]
+  -B, --batch                                  solve the puzzles on a pool of worker threads,
+                                               printing one summary line per puzzle in order
+  -w, --workers <COUNT>                        worker threads of --batch, which it implies
+                                               [default: number of CPUs]
+  -f, --format <text|summary|json>             output format [default: text], json needs the
+                                               serde feature
+  -p, --pdb-dir <DIR>                          read the pattern databases from this directory,
+                                               or save them there once built; a file which
+                                               can't be read is an error
+  -h, --help                                   print this help
+
+Exit codes:
+  0  every puzzle was solved
+  1  a puzzle is unsolvable
+  2  a limit was reached before finding a solution
+  3  a puzzle could not be read or parsed
+  64 invalid command-line arguments";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Summary,
+    // one JSON object per puzzle and per line
+    #[cfg(feature = "serde")]
+    Json,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GoalKind {
+    Standard,
+    BlankFirst,
+    Snail,
+    File(String),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    pub algo: Algo,
+    pub heuristic: Heuristics,
+    pub limits: SearchLimits,
+    pub goal: GoalKind,
+    pub threads: Option<usize>,
+    pub batch: bool,
+    pub workers: Option<usize>,
+    pub format: Format,
+    pub inputs: Vec<String>,
+    pub pdb_dir: Option<String>,
+    pub help: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            algo: Algo::ASTAR,
+            heuristic: Heuristics::LINCONFLICT,
+            limits: SearchLimits::default(),
+            goal: GoalKind::Standard,
+            threads: None,
+            batch: false,
+            workers: None,
+            format: Format::Text,
+            inputs: vec![],
+            pdb_dir: None,
+            help: false,
+        }
+    }
+}
+
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        // both "--algo idastar" and "--algo=idastar" are accepted
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value)),
+            _ => (arg.clone(), None),
+        };
+        let mut value = || -> Result<String, String> {
+            match inline_value {
+                Some(value) => Ok(value.to_string()),
+                None => args.next().ok_or(format!("missing value for {flag}")),
+            }
+        };
+        match flag.as_str() {
+            "-h" | "--help" => options.help = true,
+            "-a" | "--algo" => options.algo = value()?.parse()?,
+            "-H" | "--heuristic" => options.heuristic = value()?.parse()?,
+            "-t" | "--timeout" => {
+                let value = value()?;
+                let seconds: f64 = value
+                    .parse()
+                    .ok()
+                    .filter(|s: &f64| s.is_finite() && *s >= 0.0)
+                    .ok_or(format!("invalid timeout '{value}'"))?;
+                options.limits.timeout = Some(Duration::from_secs_f64(seconds));
+            }
+            "-n" | "--max-nodes" => {
+                let value = value()?;
+                let max_nodes = value
+                    .parse()
+                    .map_err(|_| format!("invalid node count '{value}'"))?;
+                options.limits.max_nodes = Some(max_nodes);
+            }
+            "-b" | "--max-bound" => {
+                let value = value()?;
+                let max_bound = value
+                    .parse()
+                    .map_err(|_| format!("invalid bound '{value}'"))?;
+                options.limits.max_bound = Some(max_bound);
+            }
+            "-m" | "--max-memory" => {
+                let value = value()?;
+                let megabytes: usize = value
+                    .parse()
+                    .map_err(|_| format!("invalid memory size '{value}'"))?;
+                options.limits.max_memory = Some(megabytes * 1024 * 1024);
+            }
+            "-g" | "--goal" => {
+                options.goal = match value()?.as_str() {
+                    "standard" => GoalKind::Standard,
+                    "blank-first" => GoalKind::BlankFirst,
+                    "snail" => GoalKind::Snail,
+                    file => GoalKind::File(file.to_string()),
+                }
+            }
+            "-j" | "--threads" => {
+                let value = value()?;
+                let threads = value
+                    .parse()
+                    .ok()
+                    .filter(|&threads: &usize| threads > 0)
+                    .ok_or(format!("invalid thread count '{value}'"))?;
+                options.threads = Some(threads);
+            }
+            "-B" | "--batch" => options.batch = true,
+            "-w" | "--workers" => {
+                let value = value()?;
+                let workers = value
+                    .parse()
+                    .ok()
+                    .filter(|&workers: &usize| workers > 0)
+                    .ok_or(format!("invalid worker count '{value}'"))?;
+                options.batch = true;
+                options.workers = Some(workers);
+            }
+            "-f" | "--format" => {
+                options.format = match value()?.as_str() {
+                    "text" => Format::Text,
+                    "summary" => Format::Summary,
+                    #[cfg(feature = "serde")]
+                    "json" => Format::Json,
+                    #[cfg(not(feature = "serde"))]
+                    "json" => return Err("json output needs the serde feature".to_string()),
+                    other => return Err(format!("unknown format '{other}'")),
+                }
+            }
+            "-p" | "--pdb-dir" => options.pdb_dir = Some(value()?),
+            "-" => options.inputs.push(arg),
+            _ if arg.starts_with('-') => return Err(format!("unknown option '{arg}'")),
+            _ => options.inputs.push(arg),
+        }
+    }
+    if options.inputs.is_empty() {
+        options.inputs.push("-".to_string());
+    }
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn parse_args_default_test() {
+        let options = parse_args(args("")).unwrap();
+        assert_eq!(options.algo, Algo::ASTAR);
+        assert_eq!(options.heuristic, Heuristics::LINCONFLICT);
+        assert_eq!(options.goal, GoalKind::Standard);
+        assert_eq!(options.format, Format::Text);
+        assert_eq!(options.inputs, vec!["-"]);
+        assert!(!options.batch);
+    }
+
+    #[test]
+    fn parse_args_test() {
+        let options = parse_args(args(
+            "--algo idastar -H manhattan --timeout=1.5 -n 1000 -b 80 -m 512 -g snail -j 4 -f summary -p pdb a.txt b.txt",
+        ))
+        .unwrap();
+        assert_eq!(options.algo, Algo::IDASTAR);
+        assert_eq!(options.heuristic, Heuristics::MANHATTAN);
+        assert_eq!(options.limits.timeout, Some(Duration::from_millis(1500)));
+        assert_eq!(options.limits.max_nodes, Some(1000));
+        assert_eq!(options.limits.max_bound, Some(80));
+        assert_eq!(options.limits.max_memory, Some(512 * 1024 * 1024));
+        assert_eq!(options.goal, GoalKind::Snail);
+        assert_eq!(options.threads, Some(4));
+        assert_eq!(options.format, Format::Summary);
+        assert_eq!(options.pdb_dir.as_deref(), Some("pdb"));
+        assert_eq!(options.inputs, vec!["a.txt", "b.txt"]);
+
+        let options = parse_args(args("--goal=goal.txt")).unwrap();
+        assert_eq!(options.goal, GoalKind::File("goal.txt".to_string()));
+
+        let options = parse_args(args("-w 8 test_puzzles")).unwrap();
+        assert!(options.batch);
+        assert_eq!(options.workers, Some(8));
+    }
+
+    #[test]
+    fn parse_args_errors_test() {
+        assert!(parse_args(args("--algo dijkstra")).is_err());
+        assert!(parse_args(args("--heuristic")).is_err());
+        assert!(parse_args(args("--timeout -1")).is_err());
+        assert!(parse_args(args("--max-nodes many")).is_err());
+        assert!(parse_args(args("--max-memory 1.5")).is_err());
+        assert!(parse_args(args("--threads 0")).is_err());
+        assert!(parse_args(args("--workers none")).is_err());
+        assert!(parse_args(args("--verbose")).is_err());
+        #[cfg(not(feature = "serde"))]
+        assert!(parse_args(args("--format json")).is_err());
+    }
+}
@@ -0,0 +1,249 @@
+use crate::board::{Board, Directions, Heuristics, PuzzleState};
+use crate::goal::Goal;
+use crate::solver::{Algo, Outcome, SearchLimits, Solver};
+
+use grid::Grid;
+
+// Small seedable random number generator (SplitMix64), so that the same seed always gives
+// the same puzzles
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in 0..bound, without the bias of a plain modulo
+    pub fn below(&mut self, bound: usize) -> usize {
+        let bound = bound as u64;
+        let zone = u64::MAX - u64::MAX % bound;
+        loop {
+            let value = self.next_u64();
+            if value < zone {
+                return (value % bound) as usize;
+            }
+        }
+    }
+}
+
+// Random puzzles which can all be solved towards the goal. The boards can be written to
+// puzzle files with their Display implementation.
+pub struct Generator {
+    goal: Goal,
+    rng: Rng,
+    // heuristic and limits of the solves made by with_distance
+    heuristic: Heuristics,
+    limits: SearchLimits,
+}
+
+impl Generator {
+    pub fn new(goal: Goal, seed: u64) -> Self {
+        Generator {
+            goal,
+            rng: Rng::new(seed),
+            heuristic: Heuristics::LINCONFLICT,
+            limits: SearchLimits::default(),
+        }
+    }
+
+    pub fn set_heuristic(&mut self, heuristic: Heuristics) {
+        self.heuristic = heuristic;
+    }
+
+    pub fn set_limits(&mut self, limits: SearchLimits) {
+        self.limits = limits;
+    }
+
+    // Every solvable board has the same probability: the tiles are shuffled, and two tiles
+    // are swapped back when the shuffle gives an unsolvable board, which is a bijection
+    // between the solvable and the unsolvable ones. In a single row or column only the blank
+    // can move, so the board is the goal with the blank slid to a random cell instead.
+    pub fn random_board(&mut self) -> Board {
+        let (rows, cols) = self.goal.get_size();
+        if rows == 1 || cols == 1 {
+            let cell = self.rng.below(rows * cols);
+            let mut board = self.goal.get_board().clone();
+            while board.get_pos_0() != (cell / cols, cell % cols) {
+                let (row, col) = board.get_pos_0();
+                let dir = if row < cell / cols {
+                    Directions::DOWN
+                } else if row > cell / cols {
+                    Directions::UP
+                } else if col < cell % cols {
+                    Directions::RIGHT
+                } else {
+                    Directions::LEFT
+                };
+                board.make_move(dir);
+            }
+            return board;
+        }
+        let mut tiles: Vec<usize> = (0..rows * cols).collect();
+        for i in (1..tiles.len()).rev() {
+            tiles.swap(i, self.rng.below(i + 1));
+        }
+        let blank = tiles.iter().position(|&t| t == 0).unwrap();
+        let mut board = Board::new(Grid::from_vec(tiles, cols), (blank / cols, blank % cols));
+        if !board.is_solvable(&self.goal) {
+            // the first two cells which don't hold the blank
+            let mut cells = (0..rows * cols).filter(|&cell| cell != blank);
+            let (first, second) = (cells.next().unwrap(), cells.next().unwrap());
+            let mut grid = board.get_grid().clone();
+            grid.swap((first / cols, first % cols), (second / cols, second % cols));
+            board = Board::new(grid, board.get_pos_0());
+        }
+        board
+    }
+
+    // Goal scrambled by this many random moves of the blank, never undoing the previous one.
+    // The board is at most that many moves away from the goal, with the same parity.
+    pub fn scramble(&mut self, moves: usize) -> Board {
+        let mut board = self.goal.get_board().clone();
+        self.walk(&mut board, moves);
+        board
+    }
+
+    fn walk(&mut self, board: &mut Board, moves: usize) {
+        let mut last: Option<Directions> = None;
+        for _ in 0..moves {
+            let mut directions: Vec<Directions> = board
+                .next_directions()
+                .into_iter()
+                .filter(|&dir| Some(dir.opposite()) != last)
+                .collect();
+            // in a single row or column, the blank can be stuck at an end
+            if directions.is_empty() {
+                directions = board.next_directions();
+            }
+            let dir = directions[self.rng.below(directions.len())];
+            board.make_move(dir);
+            last = Some(dir);
+        }
+    }
+
+    // Board whose optimal solution has exactly this many moves. A scramble is solved, then
+    // scrambled again by the missing moves until it is far enough, giving up after
+    // max_solves solves (a distance can be above the diameter of the puzzle) or when a solve
+    // reaches the limits.
+    pub fn with_distance(&mut self, distance: usize, max_solves: usize) -> Option<Board> {
+        let mut board = self.scramble(distance);
+        for _ in 0..max_solves {
+            let mut solver = Solver::with_goal(self.goal.clone());
+            solver.set_limits(self.limits);
+            let result = solver.solve(board.clone(), Algo::IDASTAR, self.heuristic);
+            if result.outcome != Outcome::Solved {
+                return None;
+            }
+            if result.cost == distance {
+                return Some(board);
+            }
+            self.walk(&mut board, distance - result.cost);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn rng_test() {
+        let mut first = Rng::new(7);
+        let mut second = Rng::new(7);
+        for _ in 0..100 {
+            assert_eq!(first.next_u64(), second.next_u64());
+            assert!(first.below(3) < 3);
+            second.below(3);
+        }
+        assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+    }
+
+    #[test]
+    fn random_board_test() {
+        for goal in [
+            Goal::standard(3, 3),
+            Goal::snail(4, 4),
+            Goal::standard(3, 5),
+        ] {
+            let mut generator = Generator::new(goal.clone(), 42);
+            for _ in 0..100 {
+                assert!(generator.random_board().is_solvable(&goal));
+            }
+        }
+
+        // the 12 solvable 2x2 boards come up about as often as each other
+        let mut generator = Generator::new(Goal::standard(2, 2), 3);
+        let mut counts = HashMap::new();
+        for _ in 0..12_000 {
+            *counts.entry(generator.random_board()).or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), 12);
+        assert!(counts.values().all(|&count| (800..1200).contains(&count)));
+
+        // a single row or column only has one board per position of the blank
+        for goal in [Goal::standard(1, 4), Goal::snail(4, 1)] {
+            let mut generator = Generator::new(goal.clone(), 8);
+            let mut counts = HashMap::new();
+            for _ in 0..4000 {
+                let board = generator.random_board();
+                assert!(board.is_solvable(&goal), "{board}");
+                *counts.entry(board).or_insert(0) += 1;
+            }
+            assert_eq!(counts.len(), 4);
+            assert!(counts.values().all(|&count| (800..1200).contains(&count)));
+        }
+    }
+
+    #[test]
+    fn scramble_test() {
+        let goal = Goal::standard(4, 4);
+        let mut generator = Generator::new(goal.clone(), 5);
+        let board = generator.scramble(20);
+        let mut solver = Solver::with_goal(goal.clone());
+        let result = solver.solve(board.clone(), Algo::IDASTAR, Heuristics::LINCONFLICT);
+        assert!(result.cost <= 20);
+        assert_eq!(result.cost % 2, 0);
+
+        // same seed, same boards
+        let mut generator = Generator::new(goal, 5);
+        assert_eq!(generator.scramble(20), board);
+    }
+
+    #[test]
+    fn with_distance_test() {
+        let goal = Goal::standard(3, 3);
+        let mut generator = Generator::new(goal.clone(), 9);
+        for distance in [0, 1, 12, 25] {
+            let board = generator.with_distance(distance, 100).unwrap();
+            let mut solver = Solver::with_goal(goal.clone());
+            let result = solver.solve(board.clone(), Algo::ASTAR, Heuristics::MANHATTAN);
+            assert_eq!(result.cost, distance);
+            assert_eq!(board.to_string().parse(), Ok(board));
+        }
+        let mut generator = Generator::new(Goal::standard(1, 3), 9);
+        assert_eq!(generator.scramble(3).to_string(), "1 3\n1 0 2\n");
+        // a 1x3 board is never more than 2 moves away
+        assert_eq!(generator.with_distance(33, 10), None);
+
+        // no board when the solves reach the limits
+        let mut generator = Generator::new(goal, 9);
+        generator.set_limits(SearchLimits {
+            max_nodes: Some(10),
+            ..Default::default()
+        });
+        assert_eq!(generator.with_distance(25, 100), None);
+    }
+}
@@ -0,0 +1,160 @@
+use crate::board::{Board, ParseError, PuzzleState};
+
+use grid::Grid;
+use std::str::FromStr;
+
+// Target layout of a puzzle, with the goal position of every tile
+#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+pub struct Goal {
+    board: Board,
+    positions: Vec<(usize, usize)>,
+}
+
+impl Goal {
+    pub fn from_board(board: Board) -> Self {
+        let (rows, cols) = (board.get_rows(), board.get_cols());
+        let mut positions = vec![(0, 0); rows * cols];
+        for row in 0..rows {
+            for col in 0..cols {
+                positions[board.tile(row, col)] = (row, col);
+            }
+        }
+        Goal { board, positions }
+    }
+
+    // Tiles in row-major order with the blank in the bottom-right corner
+    pub fn standard(rows: usize, cols: usize) -> Self {
+        Goal::from_board(Board::solved(rows, cols))
+    }
+
+    // Blank in the top-left corner, followed by the tiles in row-major order
+    pub fn blank_first(rows: usize, cols: usize) -> Self {
+        Goal::from_tiles(cols, (0..rows * cols).collect())
+    }
+
+    // Tiles in a clockwise spiral from the top-left corner, the blank being at the end of it
+    pub fn snail(rows: usize, cols: usize) -> Self {
+        let cells = rows * cols;
+        let mut tiles = vec![0; cells];
+        let (mut top, mut left, mut bottom, mut right) = (0, 0, rows, cols);
+        let mut value = 1;
+        let mut place = |row: usize, col: usize, value: &mut usize| {
+            if *value < cells {
+                tiles[row * cols + col] = *value;
+                *value += 1;
+            }
+        };
+        while top < bottom && left < right {
+            for col in left..right {
+                place(top, col, &mut value);
+            }
+            top += 1;
+            for row in top..bottom {
+                place(row, right - 1, &mut value);
+            }
+            right -= 1;
+            if top < bottom {
+                for col in (left..right).rev() {
+                    place(bottom - 1, col, &mut value);
+                }
+                bottom -= 1;
+            }
+            if left < right {
+                for row in (top..bottom).rev() {
+                    place(row, left, &mut value);
+                }
+                left += 1;
+            }
+        }
+        Goal::from_tiles(cols, tiles)
+    }
+
+    fn from_tiles(cols: usize, tiles: Vec<usize>) -> Self {
+        let blank = tiles.iter().position(|&t| t == 0).unwrap();
+        Goal::from_board(Board::new(
+            Grid::from_vec(tiles, cols),
+            (blank / cols, blank % cols),
+        ))
+    }
+
+    // (rows, cols) of the goal
+    pub fn get_size(&self) -> (usize, usize) {
+        (self.board.get_rows(), self.board.get_cols())
+    }
+
+    pub fn get_board(&self) -> &Board {
+        &self.board
+    }
+
+    // (row, col) of the tile in the goal
+    pub fn position(&self, tile: usize) -> (usize, usize) {
+        self.positions[tile]
+    }
+}
+
+// Parses a goal given as a puzzle file
+impl FromStr for Goal {
+    type Err = ParseError;
+
+    fn from_str(goal_str: &str) -> Result<Self, Self::Err> {
+        Ok(Goal::from_board(goal_str.parse()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grid::grid;
+
+    #[test]
+    fn standard_test() {
+        let goal = Goal::standard(3, 3);
+        assert_eq!(
+            *goal.get_board().get_grid(),
+            grid![[1, 2, 3][4, 5, 6][7, 8, 0]]
+        );
+        assert_eq!(goal.position(0), (2, 2));
+        assert_eq!(goal.position(6), (1, 2));
+    }
+
+    #[test]
+    fn blank_first_test() {
+        let goal = Goal::blank_first(3, 3);
+        assert_eq!(
+            *goal.get_board().get_grid(),
+            grid![[0, 1, 2][3, 4, 5][6, 7, 8]]
+        );
+        assert_eq!(goal.get_board().get_pos_0(), (0, 0));
+    }
+
+    #[test]
+    fn snail_test() {
+        let goal = Goal::snail(3, 3);
+        assert_eq!(
+            *goal.get_board().get_grid(),
+            grid![[1, 2, 3][8, 0, 4][7, 6, 5]]
+        );
+        assert_eq!(goal.get_board().get_pos_0(), (1, 1));
+
+        let goal = Goal::snail(4, 4);
+        assert_eq!(
+            *goal.get_board().get_grid(),
+            grid![[1, 2, 3, 4][12, 13, 14, 5][11, 0, 15, 6][10, 9, 8, 7]]
+        );
+        assert_eq!(goal.get_board().get_pos_0(), (2, 1));
+
+        let goal = Goal::snail(3, 4);
+        assert_eq!(
+            *goal.get_board().get_grid(),
+            grid![[1, 2, 3, 4][10, 11, 0, 5][9, 8, 7, 6]]
+        );
+        assert_eq!(goal.get_board().get_pos_0(), (1, 2));
+    }
+
+    #[test]
+    fn from_str_test() {
+        let goal: Goal = "2\n3 0\n2 1".parse().unwrap();
+        assert_eq!(goal.position(3), (0, 0));
+        assert_eq!(goal.position(1), (1, 1));
+    }
+}
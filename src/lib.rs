@@ -0,0 +1,12 @@
+pub mod batch;
+pub mod board;
+pub mod generator;
+pub mod goal;
+pub mod packed;
+mod parallel;
+pub mod pattern_db;
+pub mod ranking;
+pub mod reader;
+pub mod solver;
+pub mod state_space;
+pub mod walking_distance;
@@ -1,48 +1,543 @@
-pub mod board;
-pub mod solver;
-
-use crate::{board::Board, solver::Algo, solver::Solver};
-use std::{fs, time};
-
-pub fn load_board(puzzle_name: &str) -> Board {
-    let puzzle_str = fs::read_to_string(puzzle_name).expect("Could not read file");
-    let mut puzzle_in = puzzle_str.splitn(2, '\n');
-    let n: usize = puzzle_in
-        .next()
-        .expect("Error reading the first line, maybe the file is empty?")
-        .parse()
-        .expect("Impossible to parse this str to usize");
-    // println!("N = {n}");
-    let puzzle = puzzle_in
-        .next()
-        .expect("File only contains the puzzle size");
-    // println!("{puzzle}");
-    let board = Board::load_from_str(n, puzzle);
-    return board;
-}
-
-fn main() {
-    for path in fs::read_dir("test_puzzles").unwrap() {
-        let path_str = path.unwrap().path();
-        let puzzle_name = path_str.to_str().unwrap();
-        if puzzle_name.len() != 25 {
-            continue;
-        }
-        let time_start = time::SystemTime::now();
-        let board = load_board(puzzle_name);
-        let mut solver = Solver::new(board.get_n());
-        solver.solve(board, Algo::ASTAR, board::Heuristics::LINCONFLICT);
-        println!("elapsed time: {:?}\n", (time_start.elapsed().unwrap()));
+mod cli;
+
+use cli::{Format, GoalKind, Options};
+use rust_idastar::{
+    batch::{BatchPuzzle, BatchSolver},
+    board::{Board, Heuristics, ParseError, PuzzleState},
+    goal::Goal,
+    pattern_db::{self, PdbError},
+    reader::PuzzleReader,
+    solver::{Outcome, SolveResult, Solver, StdoutObserver},
+    walking_distance::WalkingDistance,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    env,
+    error::Error,
+    fs::{self, File},
+    io::{self, BufRead, BufReader},
+    iter,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::Mutex,
+};
+
+const EXIT_UNSOLVABLE: u8 = 1;
+const EXIT_LIMIT_REACHED: u8 = 2;
+const EXIT_PARSE_ERROR: u8 = 3;
+const EXIT_USAGE: u8 = 64;
+
+// Something read from the inputs with its name, or the reason it could not be read
+type Named<T> = (String, Result<T, Box<dyn Error + Send + Sync>>);
+
+// Puzzles read one at a time, the batch workers taking them as they go
+type Puzzles<'a, T> = Box<dyn Iterator<Item = Named<T>> + Send + 'a>;
+
+// Puzzles of an input: every file of a directory, or every puzzle of a file, numbered after
+// the file name when there are several. They are read as they are asked for.
+fn load_boards(input: &str) -> Puzzles<'static, Board> {
+    if input != "-" && Path::new(input).is_dir() {
+        let mut files: Vec<PathBuf> = match fs::read_dir(input) {
+            Ok(entries) => entries
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .filter(|path| path.is_file())
+                .collect(),
+            Err(e) => return Box::new(iter::once((input.to_string(), Err(e.into())))),
+        };
+        files.sort();
+        return Box::new(
+            files
+                .into_iter()
+                .flat_map(|file| load_boards(&file.to_string_lossy())),
+        );
+    }
+    let reader: Box<dyn BufRead + Send> = if input == "-" {
+        Box::new(BufReader::new(io::stdin()))
+    } else {
+        match File::open(input) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(e) => return Box::new(iter::once((input.to_string(), Err(e.into())))),
+        }
+    };
+    let input = input.to_string();
+    let mut boards = PuzzleReader::new(reader).peekable();
+    let mut count = 0;
+    Box::new(iter::from_fn(move || {
+        let board = match boards.next() {
+            Some(board) => board,
+            None if count == 0 => Err(ParseError::Empty),
+            None => return None,
+        };
+        count += 1;
+        // a file holding a single puzzle keeps its name
+        let name = if count == 1 && boards.peek().is_none() {
+            input.clone()
+        } else {
+            format!("{input}#{count}")
+        };
+        Some((name, board.map_err(Into::into)))
+    }))
+}
+
+// Every puzzle of the inputs with its goal, the goals being only loaded once per size
+fn load_puzzles(options: &Options) -> Puzzles<'_, BatchPuzzle> {
+    let mut goals: HashMap<(usize, usize), Goal> = HashMap::new();
+    let mut puzzle = move |board: Board| -> Result<BatchPuzzle, Box<dyn Error + Send + Sync>> {
+        let size = (board.get_rows(), board.get_cols());
+        let goal = match goals.get(&size) {
+            Some(goal) => goal.clone(),
+            None => {
+                let goal = load_goal(&options.goal, size.0, size.1)?;
+                if options.heuristic == Heuristics::PATTERNDB {
+                    load_database(&goal, options)?;
+                }
+                if options.heuristic == Heuristics::WALKINGDISTANCE
+                    && WalkingDistance::for_goal(&goal).is_none()
+                {
+                    eprintln!(
+                        "{}x{}: walking distance needs at most 4 rows and 4 columns, using Linear Conflicts",
+                        size.0, size.1
+                    );
+                }
+                goals.insert(size, goal.clone());
+                goal
+            }
+        };
+        Ok(BatchPuzzle { board, goal })
+    };
+    Box::new(
+        options
+            .inputs
+            .iter()
+            .flat_map(|input| load_boards(input))
+            .map(move |(name, board)| (name, board.and_then(&mut puzzle))),
+    )
+}
+
+// Goal of a puzzle of this size
+fn load_goal(
+    kind: &GoalKind,
+    rows: usize,
+    cols: usize,
+) -> Result<Goal, Box<dyn Error + Send + Sync>> {
+    let goal = match kind {
+        GoalKind::Standard => Goal::standard(rows, cols),
+        GoalKind::BlankFirst => Goal::blank_first(rows, cols),
+        GoalKind::Snail => Goal::snail(rows, cols),
+        GoalKind::File(goal_name) => fs::read_to_string(goal_name)?.parse()?,
+    };
+    let (goal_rows, goal_cols) = goal.get_size();
+    if (goal_rows, goal_cols) != (rows, cols) {
+        return Err(
+            format!("the goal is {goal_rows}x{goal_cols} but the puzzle is {rows}x{cols}").into(),
+        );
     }
+    Ok(goal)
+}
+
+// Loads the pattern database of the goal before the searches, which can't report the errors of
+// the cache directory. A database which could not be saved is still used.
+fn load_database(goal: &Goal, options: &Options) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let dir = Path::new(options.pdb_dir.as_deref().unwrap_or_default());
+    let path = pattern_db::cache_file(dir, goal);
+    match pattern_db::load(goal) {
+        Ok(_) => Ok(()),
+        Err(e @ PdbError::Save(_)) => {
+            eprintln!("{}: {e}", path.display());
+            Ok(())
+        }
+        Err(e) => Err(format!("{}: {e}", path.display()).into()),
+    }
+}
+
+fn exit_code(outcome: Outcome) -> u8 {
+    match outcome {
+        Outcome::Solved => 0,
+        Outcome::Unsolvable => EXIT_UNSOLVABLE,
+        Outcome::LimitReached(_) | Outcome::Cancelled => EXIT_LIMIT_REACHED,
+    }
+}
+
+// The text format ends the log of the search, which batches don't print
+fn print_result(puzzle_name: &str, result: &SolveResult, options: &Options) {
+    match options.format {
+        Format::Text if !options.batch => println!("elapsed time: {:?}\n", result.elapsed),
+        Format::Text | Format::Summary => println!(
+            "{puzzle_name}: {}, {} moves, {} nodes expanded, {:?}",
+            result.outcome, result.cost, result.nodes_expanded, result.elapsed
+        ),
+        #[cfg(feature = "serde")]
+        Format::Json => println!("{}", json_record(puzzle_name, result, options)),
+    }
+}
+
+#[cfg(feature = "serde")]
+fn json_record(puzzle_name: &str, result: &SolveResult, options: &Options) -> String {
+    use rust_idastar::solver::Algo;
+
+    #[derive(serde::Serialize)]
+    struct Record<'a> {
+        puzzle: &'a str,
+        algo: Algo,
+        heuristic: Heuristics,
+        #[serde(flatten)]
+        result: &'a SolveResult,
+    }
+    let record = Record {
+        puzzle: puzzle_name,
+        algo: options.algo,
+        heuristic: options.heuristic,
+        result,
+    };
+    serde_json::to_string(&record).unwrap()
+}
+
+fn run(options: &Options) -> u8 {
+    let puzzles = load_puzzles(options);
+    if options.batch {
+        return run_batch(options, puzzles);
+    }
+    let mut code = 0;
+    for (puzzle_name, puzzle) in puzzles {
+        let puzzle = match puzzle {
+            Ok(puzzle) => puzzle,
+            Err(e) => {
+                eprintln!("{puzzle_name}: {e}");
+                code = code.max(EXIT_PARSE_ERROR);
+                continue;
+            }
+        };
+        let mut solver = Solver::with_goal(puzzle.goal.clone());
+        solver.set_limits(options.limits);
+        if let Some(threads) = options.threads {
+            solver.set_threads(threads);
+        }
+        if options.format == Format::Text {
+            println!("{puzzle_name}");
+            solver.set_observer(Box::new(StdoutObserver::new()));
+        }
+        let result = solver.solve(puzzle.board, options.algo, options.heuristic);
+        print_result(&puzzle_name, &result, options);
+        code = code.max(exit_code(result.outcome));
+    }
+    code
+}
+
+// Solves the puzzles on the worker pool, with one summary line per puzzle in the same order
+fn run_batch(options: &Options, puzzles: Puzzles<BatchPuzzle>) -> u8 {
+    let mut batch = BatchSolver::new(options.algo, options.heuristic);
+    batch.set_limits(options.limits);
+    if let Some(workers) = options.workers {
+        batch.set_workers(workers);
+    }
+    if let Some(threads) = options.threads {
+        batch.set_threads(threads);
+    }
+    // names of the puzzles read by the workers and not reported yet, with the errors of the
+    // ones which could not be loaded
+    let names: Mutex<VecDeque<Named<()>>> = Mutex::new(VecDeque::new());
+    let valid = puzzles.filter_map(|(puzzle_name, puzzle)| {
+        let (puzzle, name) = match puzzle {
+            Ok(puzzle) => (Some(puzzle), (puzzle_name, Ok(()))),
+            Err(e) => (None, (puzzle_name, Err(e))),
+        };
+        names.lock().unwrap().push_back(name);
+        puzzle
+    });
+
+    let mut code = 0;
+    // the puzzles which could not be loaded are reported at their place among the results
+    let report_errors = |code: &mut u8| loop {
+        let next = names.lock().unwrap().pop_front();
+        match next? {
+            (puzzle_name, Ok(())) => return Some(puzzle_name),
+            (puzzle_name, Err(e)) => {
+                eprintln!("{puzzle_name}: {e}");
+                *code = (*code).max(EXIT_PARSE_ERROR);
+            }
+        }
+    };
+    batch.solve_each(valid, |_, result| {
+        let puzzle_name = report_errors(&mut code).unwrap();
+        print_result(&puzzle_name, &result, options);
+        code = code.max(exit_code(result.outcome));
+    });
+    report_errors(&mut code);
+    code
+}
+
+fn main() -> ExitCode {
+    let options = match cli::parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}\n\n{}", cli::USAGE);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+    if options.help {
+        println!("{}", cli::USAGE);
+        return ExitCode::SUCCESS;
+    }
+    if let Some(dir) = &options.pdb_dir {
+        pattern_db::set_cache_dir(dir);
+    }
+    ExitCode::from(run(&options))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rust_idastar::pattern_db::PatternDatabase;
+    use rust_idastar::solver::{Algo, verify_solution};
+
+    fn load_board(puzzle_name: &str) -> Result<Board, Box<dyn Error + Send + Sync>> {
+        let mut boards: Vec<_> = load_boards(puzzle_name).collect();
+        assert_eq!(boards.len(), 1);
+        boards.pop().unwrap().1
+    }
 
     #[test]
     fn load_board_test() {
-        let board = load_board("test_puzzles/puzzle00.txt");
-        assert_eq!(board.get_n(), 10);
+        let board = load_board("test_puzzles/puzzle00.txt").unwrap();
+        assert_eq!((board.get_rows(), board.get_cols()), (10, 10));
+    }
+
+    #[test]
+    fn load_boards_test() {
+        let boards: Vec<_> = load_boards("test_puzzles").collect();
+        assert!(boards.len() > 50);
+        assert_eq!(boards[0].0, "test_puzzles/puzzle00.txt");
+        assert!(boards.iter().all(|(_, board)| board.is_ok()));
+
+        let boards: Vec<_> = load_boards("test_collections/small.txt").collect();
+        assert_eq!(boards.len(), 21);
+        assert_eq!(boards[0].0, "test_collections/small.txt#1");
+        assert_eq!(boards[20].0, "test_collections/small.txt#21");
+        assert!(boards.iter().all(|(_, board)| board.is_ok()));
+        let mut boards = load_boards("test_puzzles/does_not_exist.txt");
+        assert!(boards.next().unwrap().1.is_err());
+        assert!(boards.next().is_none());
+    }
+
+    #[test]
+    fn unsolvable_puzzles_test() {
+        for path in fs::read_dir("test_puzzles").unwrap() {
+            let path_str = path.unwrap().path();
+            let puzzle_name = path_str.to_str().unwrap();
+            if !puzzle_name.contains("unsolvable") {
+                continue;
+            }
+            let board = load_board(puzzle_name).unwrap();
+            let mut solver = Solver::new(board.get_rows(), board.get_cols());
+            let result = solver.solve(board, Algo::IDASTAR, Heuristics::LINCONFLICT);
+            assert_eq!(result.outcome, Outcome::Unsolvable, "{puzzle_name}");
+        }
+    }
+
+    #[test]
+    fn solvable_puzzles_test() {
+        for path in fs::read_dir("test_puzzles").unwrap() {
+            let path_str = path.unwrap().path();
+            let puzzle_name = path_str.to_str().unwrap();
+            if puzzle_name.contains("unsolvable") {
+                continue;
+            }
+            let board = load_board(puzzle_name).unwrap();
+            let goal = Goal::standard(board.get_rows(), board.get_cols());
+            assert!(board.is_solvable(&goal), "{puzzle_name}");
+        }
+    }
+
+    // Registers the pattern database of the 4x4 puzzle, kept in target/pdb so that only the
+    // first run of the tests builds it
+    fn load_4x4_database() {
+        let goal = Goal::standard(4, 4);
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("target")
+            .join("pdb");
+        let path = pattern_db::cache_file(&dir, &goal);
+        let database = PatternDatabase::load_file(&path, goal.get_board()).unwrap_or_else(|_| {
+            let partition = PatternDatabase::default_partition(4, 4).unwrap();
+            let database = PatternDatabase::build(goal.get_board(), &partition);
+            fs::create_dir_all(&dir).unwrap();
+            database.save_file(&path).unwrap();
+            database
+        });
+        pattern_db::register(database);
+    }
+
+    #[test]
+    fn fixture_solutions_test() {
+        // the other fixtures with a known number of moves, the hardest ones needing the pattern
+        // database to be solved in seconds
+        let named = HashMap::from([
+            ("puzzle3x4", (32, Heuristics::LINCONFLICT)),
+            ("puzzle4x4-hard1", (38, Heuristics::PATTERNDB)),
+            ("puzzle4x4-hard2", (47, Heuristics::PATTERNDB)),
+            ("puzzle4x4-78", (78, Heuristics::PATTERNDB)),
+            ("puzzle4x4-80", (80, Heuristics::PATTERNDB)),
+        ]);
+        load_4x4_database();
+        for (puzzle_name, board) in load_boards("test_puzzles") {
+            let name = puzzle_name
+                .strip_prefix("test_puzzles/")
+                .and_then(|name| name.strip_suffix(".txt"))
+                .unwrap();
+            // every puzzleXX.txt needs XX moves, and the unsolvable ones are checked by
+            // unsolvable_puzzles_test
+            let (moves, heuristic) = match name.strip_prefix("puzzle").map(str::parse::<usize>) {
+                Some(Ok(moves)) => (moves, Heuristics::LINCONFLICT),
+                _ if name.contains("unsolvable") => continue,
+                _ => *named
+                    .get(name)
+                    .unwrap_or_else(|| panic!("{puzzle_name} has no known outcome")),
+            };
+            let board = board.unwrap();
+            let goal = Goal::standard(board.get_rows(), board.get_cols());
+            let mut solver = Solver::with_goal(goal.clone());
+            let result = solver.solve(board.clone(), Algo::IDASTAR, heuristic);
+            assert_eq!(result.outcome, Outcome::Solved, "{puzzle_name}");
+            assert_eq!(
+                verify_solution(&board, &goal, &result.path),
+                Ok(()),
+                "{puzzle_name}"
+            );
+            assert_eq!(result.path.len(), result.cost, "{puzzle_name}");
+            assert_eq!(result.cost, moves, "{puzzle_name}");
+        }
+    }
+
+    #[test]
+    fn load_board_missing_file_test() {
+        assert!(load_board("test_puzzles/does_not_exist.txt").is_err());
+    }
+
+    #[test]
+    fn load_goal_test() {
+        let goal = load_goal(&GoalKind::Snail, 3, 3).unwrap();
+        assert_eq!(goal, Goal::snail(3, 3));
+        let kind = GoalKind::File("test_puzzles/puzzle04.txt".to_string());
+        assert_eq!(load_goal(&kind, 3, 3).unwrap().position(0), (0, 0));
+        assert!(load_goal(&kind, 3, 4).is_err());
+        assert!(load_goal(&GoalKind::File("does_not_exist.txt".to_string()), 3, 3).is_err());
+    }
+
+    #[test]
+    fn run_test() {
+        let mut options = cli::parse_args(["test_puzzles/puzzle04.txt".to_string()]).unwrap();
+        options.format = Format::Summary;
+        assert_eq!(run(&options), 0);
+
+        options
+            .inputs
+            .push("test_puzzles/puzzle3x3-unsolvable.txt".to_string());
+        assert_eq!(run(&options), EXIT_UNSOLVABLE);
+
+        options.inputs = vec!["test_puzzles/puzzle4x4-hard1.txt".to_string()];
+        options.limits.max_nodes = Some(10);
+        assert_eq!(run(&options), EXIT_LIMIT_REACHED);
+
+        options
+            .inputs
+            .push("test_puzzles/does_not_exist.txt".to_string());
+        assert_eq!(run(&options), EXIT_PARSE_ERROR);
+    }
+
+    #[test]
+    fn pdb_dir_test() {
+        let dir = std::env::temp_dir().join(format!("pdb-dir-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let goal_file = dir.join("goal.txt");
+        fs::write(&goal_file, "2\n3 1\n2 0").unwrap();
+        let puzzle_file = dir.join("puzzle.txt");
+        fs::write(&puzzle_file, "2\n3 1\n0 2").unwrap();
+        let goal: Goal = fs::read_to_string(&goal_file).unwrap().parse().unwrap();
+        let cache_file = pattern_db::cache_file(&dir, &goal);
+        fs::write(&cache_file, b"PDB?").unwrap();
+
+        let mut options = Options {
+            heuristic: Heuristics::PATTERNDB,
+            goal: GoalKind::File(goal_file.to_string_lossy().into_owned()),
+            format: Format::Summary,
+            inputs: vec![puzzle_file.to_string_lossy().into_owned()],
+            pdb_dir: Some(dir.to_string_lossy().into_owned()),
+            ..Default::default()
+        };
+        pattern_db::set_cache_dir(&dir);
+        // the corrupted file is reported, not overwritten
+        assert_eq!(run(&options), EXIT_PARSE_ERROR);
+        assert_eq!(fs::read(&cache_file).unwrap(), b"PDB?");
+
+        fs::remove_file(&cache_file).unwrap();
+        options.batch = true;
+        assert_eq!(run(&options), 0);
+        assert!(cache_file.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_record_test() {
+        use rust_idastar::solver::{Limit, SearchLimits};
+
+        let mut options =
+            cli::parse_args("-a idastar -f json".split(' ').map(String::from)).unwrap();
+        let board = load_board("test_puzzles/puzzle04.txt").unwrap();
+        let mut solver = Solver::new(3, 3);
+        let result = solver.solve(board, options.algo, options.heuristic);
+        let record: serde_json::Value =
+            serde_json::from_str(&json_record("puzzle04", &result, &options)).unwrap();
+        assert_eq!(record["puzzle"], "puzzle04");
+        assert_eq!(record["algo"], "idastar");
+        assert_eq!(record["heuristic"], "linconflict");
+        assert_eq!(record["outcome"], serde_json::json!({"status": "solved"}));
+        assert_eq!(record["cost"], 4);
+        assert_eq!(
+            record["path"],
+            serde_json::json!(["right", "down", "right", "down"])
+        );
+        assert!(record["elapsed"].is_f64());
+
+        // every outcome has the same shape
+        let board = load_board("test_puzzles/puzzle4x4-hard1.txt").unwrap();
+        let mut solver = Solver::new(4, 4);
+        solver.set_limits(SearchLimits {
+            max_nodes: Some(10),
+            ..Default::default()
+        });
+        let result = solver.solve(board, options.algo, options.heuristic);
+        let record: serde_json::Value =
+            serde_json::from_str(&json_record("hard1", &result, &options)).unwrap();
+        assert_eq!(
+            record["outcome"],
+            serde_json::json!({"status": "limit_reached", "limit": "max_nodes"})
+        );
+        let outcome: Outcome = serde_json::from_value(record["outcome"].clone()).unwrap();
+        assert_eq!(outcome, Outcome::LimitReached(Limit::MaxNodes));
+
+        options.inputs = vec!["test_puzzles/puzzle3x4.txt".to_string()];
+        options.batch = true;
+        assert_eq!(run(&options), 0);
+    }
+
+    #[test]
+    fn run_batch_test() {
+        let mut options = cli::parse_args(["test_puzzles".to_string()]).unwrap();
+        options.batch = true;
+        options.algo = Algo::IDASTAR;
+        options.limits.max_nodes = Some(10_000);
+        assert_eq!(
+            load_puzzles(&options).count(),
+            fs::read_dir("test_puzzles").unwrap().count()
+        );
+        assert_eq!(run(&options), EXIT_LIMIT_REACHED);
+
+        options.inputs = vec![
+            "test_puzzles/puzzle04.txt".to_string(),
+            "test_puzzles/does_not_exist.txt".to_string(),
+            "test_puzzles/puzzle3x4.txt".to_string(),
+        ];
+        options.limits.max_nodes = None;
+        assert_eq!(run(&options), EXIT_PARSE_ERROR);
     }
 }
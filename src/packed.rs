@@ -0,0 +1,240 @@
+use crate::board::{Board, Directions, PuzzleState};
+
+// Boards up to 16 cells (4x4, 2x8...), stored on 4 bits per tile in row-major order
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
+pub struct PackedBoard {
+    tiles: u64,
+    rows: u8,
+    cols: u8,
+    pos_0: u8,
+}
+
+// Bigger boards (up to 256 cells), stored on one byte per tile
+#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+pub struct PackedVecBoard {
+    tiles: Box<[u8]>,
+    rows: u16,
+    cols: u16,
+    pos_0: u16,
+}
+
+impl PackedBoard {
+    pub const MAX_CELLS: usize = 16;
+
+    pub fn from_state(state: &impl PuzzleState) -> Option<Self> {
+        let (rows, cols) = (state.get_rows(), state.get_cols());
+        if rows * cols > Self::MAX_CELLS {
+            return None;
+        }
+        let mut tiles = 0;
+        for row in 0..rows {
+            for col in 0..cols {
+                tiles |= (state.tile(row, col) as u64) << (4 * (row * cols + col));
+            }
+        }
+        let (row, col) = state.get_pos_0();
+        Some(PackedBoard {
+            tiles,
+            rows: rows as u8,
+            cols: cols as u8,
+            pos_0: (row * cols + col) as u8,
+        })
+    }
+
+    fn get(&self, index: usize) -> u64 {
+        (self.tiles >> (4 * index)) & 0xF
+    }
+}
+
+impl PuzzleState for PackedBoard {
+    fn get_rows(&self) -> usize {
+        self.rows as usize
+    }
+
+    fn get_cols(&self) -> usize {
+        self.cols as usize
+    }
+
+    fn tile(&self, row: usize, col: usize) -> usize {
+        self.get(row * self.cols as usize + col) as usize
+    }
+
+    fn get_pos_0(&self) -> (usize, usize) {
+        let cols = self.cols as usize;
+        (self.pos_0 as usize / cols, self.pos_0 as usize % cols)
+    }
+
+    fn make_move(&mut self, move_d: Directions) {
+        let blank = self.pos_0 as usize;
+        let target = neighbor(blank, self.cols as usize, move_d);
+        // the blank is 0, so clearing the moved tile and writing it at the blank is a swap
+        let value = self.get(target);
+        self.tiles &= !(0xF << (4 * target));
+        self.tiles |= value << (4 * blank);
+        self.pos_0 = target as u8;
+    }
+}
+
+impl PackedVecBoard {
+    pub const MAX_CELLS: usize = 256;
+
+    pub fn from_state(state: &impl PuzzleState) -> Option<Self> {
+        let (rows, cols) = (state.get_rows(), state.get_cols());
+        if rows * cols > Self::MAX_CELLS {
+            return None;
+        }
+        let mut tiles = Vec::with_capacity(rows * cols);
+        for row in 0..rows {
+            for col in 0..cols {
+                tiles.push(state.tile(row, col) as u8);
+            }
+        }
+        let (row, col) = state.get_pos_0();
+        Some(PackedVecBoard {
+            tiles: tiles.into_boxed_slice(),
+            rows: rows as u16,
+            cols: cols as u16,
+            pos_0: (row * cols + col) as u16,
+        })
+    }
+}
+
+impl PuzzleState for PackedVecBoard {
+    fn get_rows(&self) -> usize {
+        self.rows as usize
+    }
+
+    fn get_cols(&self) -> usize {
+        self.cols as usize
+    }
+
+    fn tile(&self, row: usize, col: usize) -> usize {
+        self.tiles[row * self.cols as usize + col] as usize
+    }
+
+    fn get_pos_0(&self) -> (usize, usize) {
+        let cols = self.cols as usize;
+        (self.pos_0 as usize / cols, self.pos_0 as usize % cols)
+    }
+
+    fn make_move(&mut self, move_d: Directions) {
+        let blank = self.pos_0 as usize;
+        let target = neighbor(blank, self.cols as usize, move_d);
+        self.tiles.swap(blank, target);
+        self.pos_0 = target as u16;
+    }
+
+    fn heap_size(&self) -> usize {
+        self.tiles.len()
+    }
+}
+
+impl From<&PackedBoard> for Board {
+    fn from(state: &PackedBoard) -> Self {
+        Board::from_state(state)
+    }
+}
+
+impl From<&PackedVecBoard> for Board {
+    fn from(state: &PackedVecBoard) -> Self {
+        Board::from_state(state)
+    }
+}
+
+// Row-major index of the cell where the blank goes
+fn neighbor(blank: usize, cols: usize, move_d: Directions) -> usize {
+    match move_d {
+        Directions::UP => blank - cols,
+        Directions::DOWN => blank + cols,
+        Directions::LEFT => blank - 1,
+        Directions::RIGHT => blank + 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Heuristics;
+    use crate::goal::Goal;
+
+    #[test]
+    fn packed_board_test() {
+        /*
+        4
+         3  2  4  8
+         1  6  0 12
+         5 10  7 11
+         9 13 14 15
+        */
+        let board: Board = "4\n3 2 4 8\n1 6 0 12\n5 10 7 11\n9 13 14 15"
+            .parse()
+            .unwrap();
+        let packed = PackedBoard::from_state(&board).unwrap();
+        assert_eq!(Board::from(&packed), board);
+        assert_eq!(packed.get_pos_0(), (1, 2));
+        assert_eq!(packed.tile(2, 1), 10);
+        assert_eq!(packed.next_directions(), board.next_directions());
+        let goal = Goal::standard(4, 4);
+        for heuristic in [Heuristics::MANHATTAN, Heuristics::LINCONFLICT] {
+            assert_eq!(
+                packed.heuristic(heuristic, &goal),
+                board.heuristic(heuristic, &goal)
+            );
+        }
+        assert_eq!(packed.is_solvable(&goal), board.is_solvable(&goal));
+    }
+
+    #[test]
+    fn packed_make_move_test() {
+        let board = Board::load_from_str(3, 3, "2 1 3\n4 0 5\n7 8 6").unwrap();
+        let mut packed = PackedBoard::from_state(&board).unwrap();
+        let mut packed_vec = PackedVecBoard::from_state(&board).unwrap();
+        let mut expected = board.clone();
+        for dir in [
+            Directions::UP,
+            Directions::LEFT,
+            Directions::DOWN,
+            Directions::DOWN,
+            Directions::RIGHT,
+            Directions::RIGHT,
+        ] {
+            packed.make_move(dir);
+            packed_vec.make_move(dir);
+            expected.make_move(dir);
+            assert_eq!(Board::from(&packed), expected);
+            assert_eq!(Board::from(&packed_vec), expected);
+            // ranked without going back to a Board
+            assert_eq!(packed.rank(), expected.rank());
+            assert_eq!(packed_vec.rank(), expected.rank());
+        }
+    }
+
+    #[test]
+    fn too_big_test() {
+        let board = Board::solved(5, 5);
+        assert!(PackedBoard::from_state(&board).is_none());
+        let packed_vec = PackedVecBoard::from_state(&board).unwrap();
+        assert_eq!(Board::from(&packed_vec), board);
+        assert!(PackedVecBoard::from_state(&Board::solved(16, 17)).is_none());
+    }
+
+    #[test]
+    fn rectangular_test() {
+        /*
+        2 4
+        1 2 0 4
+        5 6 3 7
+        */
+        let board: Board = "2 4\n1 2 0 4\n5 6 3 7".parse().unwrap();
+        let mut packed = PackedBoard::from_state(&board).unwrap();
+        let mut packed_vec = PackedVecBoard::from_state(&board).unwrap();
+        assert_eq!(packed.get_pos_0(), (0, 2));
+        assert_eq!(packed.next_directions(), board.next_directions());
+        for dir in [Directions::DOWN, Directions::RIGHT] {
+            packed.make_move(dir);
+            packed_vec.make_move(dir);
+        }
+        assert_eq!(Board::from(&packed), Board::solved(2, 4));
+        assert_eq!(Board::from(&packed_vec), Board::solved(2, 4));
+    }
+}
@@ -0,0 +1,357 @@
+use crate::board::{Directions, Estimate, Evaluator, PuzzleState};
+use crate::solver::{CancelToken, Limit, SearchLimits, TICK_NODES};
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Instant;
+
+// Every thread gets this many subtrees on average, so that a thread finishing early can take
+// work left by the others
+const ITEMS_PER_THREAD: usize = 16;
+// The subtrees are all kept in memory, however many threads are asked for
+const MAX_ITEMS: usize = 1 << 16;
+
+// Settings of one parallel IDA* iteration
+pub(crate) struct Iteration<'a> {
+    pub evaluator: &'a Evaluator,
+    pub bound: usize,
+    pub limits: SearchLimits,
+    pub time_start: Instant,
+    pub cancel_token: Option<&'a CancelToken>,
+    // nodes expanded by the previous iterations, for SearchLimits::max_nodes
+    pub nodes_before: usize,
+    pub threads: usize,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct IterationResult {
+    pub solution: Option<Vec<Directions>>,
+    pub min_exceeded: usize,
+    pub nodes_expanded: usize,
+    pub nodes_generated: usize,
+    pub lowest_h: Option<usize>,
+    pub limit_reached: Option<Limit>,
+    pub cancelled: bool,
+}
+
+// Why an iteration stops before having searched its whole tree, other than a solution
+enum Stop {
+    Limit(Limit),
+    Cancelled,
+}
+
+impl Iteration<'_> {
+    // Checks the limits once the iteration expanded this many nodes and uses this much memory,
+    // and the cancellation. The clock is only read on ticks.
+    fn stop(&self, expanded: usize, memory: usize, tick: bool) -> Option<Stop> {
+        if self.cancel_token.is_some_and(|token| token.is_cancelled()) {
+            return Some(Stop::Cancelled);
+        }
+        if let Some(max_nodes) = self.limits.max_nodes
+            && self.nodes_before + expanded > max_nodes
+        {
+            return Some(Stop::Limit(Limit::MaxNodes));
+        }
+        if let Some(max_memory) = self.limits.max_memory
+            && memory > max_memory
+        {
+            return Some(Stop::Limit(Limit::Memory));
+        }
+        if let Some(timeout) = self.limits.timeout
+            && tick
+            && self.time_start.elapsed() > timeout
+        {
+            return Some(Stop::Limit(Limit::Timeout));
+        }
+        None
+    }
+}
+
+// A subtree below the split depth: its root, heuristic, and the moves leading to it
+struct WorkItem<S> {
+    board: S,
+    estimate: Estimate,
+    path: Vec<Directions>,
+}
+
+impl<S: PuzzleState> WorkItem<S> {
+    fn memory(&self) -> usize {
+        size_of::<Self>() + self.board.heap_size() + self.path.capacity() * size_of::<Directions>()
+    }
+}
+
+// State shared by the threads of an iteration
+struct Shared<'a> {
+    iteration: &'a Iteration<'a>,
+    next_item: AtomicUsize,
+    // memory of the work items, and number of threads searching them
+    items_memory: usize,
+    threads: usize,
+    // set once a solution is found, a limit is reached or the search is cancelled
+    stop: AtomicBool,
+    nodes_expanded: AtomicUsize,
+    nodes_generated: AtomicUsize,
+    lowest_h: AtomicUsize,
+    solution: Mutex<Option<Vec<Directions>>>,
+    limit_reached: Mutex<Option<Limit>>,
+    cancelled: AtomicBool,
+}
+
+// Depth-first search of some subtrees by one thread. The counters are only added to the
+// shared ones every TICK_NODES nodes.
+struct Worker<'a, 'b, S> {
+    shared: &'b Shared<'a>,
+    target: &'b S,
+    nodes_expanded: usize,
+    nodes_generated: usize,
+    lowest_h: usize,
+    // board and path of the thread at its last tick
+    memory: usize,
+}
+
+// Runs one iteration of IDA*: the tree is expanded breadth-first until there are enough
+// subtrees for every thread, then the subtrees are searched depth-first in parallel.
+// Any solution found within the bound is optimal, so the first one stops every thread.
+pub(crate) fn run_iteration<S: PuzzleState + Send + Sync>(
+    iteration: &Iteration,
+    root: &S,
+    root_estimate: Estimate,
+    target: &S,
+) -> IterationResult {
+    let mut result = IterationResult {
+        min_exceeded: usize::MAX,
+        ..Default::default()
+    };
+    let items = match split(iteration, root, root_estimate, target, &mut result) {
+        Ok(items) => items,
+        Err(solution) => {
+            result.solution = Some(solution);
+            return result;
+        }
+    };
+    // a limit was reached, or the whole tree within the bound is already expanded
+    if items.is_empty() {
+        return result;
+    }
+
+    let threads = iteration.threads.min(items.len());
+    let shared = Shared {
+        iteration,
+        next_item: AtomicUsize::new(0),
+        items_memory: items.iter().map(WorkItem::memory).sum(),
+        threads,
+        stop: AtomicBool::new(false),
+        nodes_expanded: AtomicUsize::new(result.nodes_expanded),
+        nodes_generated: AtomicUsize::new(result.nodes_generated),
+        lowest_h: AtomicUsize::new(result.lowest_h.unwrap_or(usize::MAX)),
+        solution: Mutex::new(None),
+        limit_reached: Mutex::new(None),
+        cancelled: AtomicBool::new(false),
+    };
+    let min_exceeded = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut worker = Worker {
+                        shared: &shared,
+                        target,
+                        nodes_expanded: 0,
+                        nodes_generated: 0,
+                        lowest_h: usize::MAX,
+                        memory: 0,
+                    };
+                    worker.run(&items)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .min()
+            .unwrap_or(usize::MAX)
+    });
+
+    result.min_exceeded = result.min_exceeded.min(min_exceeded);
+    result.nodes_expanded = shared.nodes_expanded.into_inner();
+    result.nodes_generated = shared.nodes_generated.into_inner();
+    result.lowest_h = Some(shared.lowest_h.into_inner()).filter(|&h| h != usize::MAX);
+    result.solution = shared.solution.into_inner().unwrap();
+    result.limit_reached = shared.limit_reached.into_inner().unwrap();
+    result.cancelled = shared.cancelled.into_inner();
+    result
+}
+
+// Breadth-first expansion of the top of the tree, with the same pruning and limits as the
+// depth-first search. Returns the solution instead if it is found there, and no items when a
+// limit is reached.
+fn split<S: PuzzleState>(
+    iteration: &Iteration,
+    root: &S,
+    root_estimate: Estimate,
+    target: &S,
+    result: &mut IterationResult,
+) -> Result<Vec<WorkItem<S>>, Vec<Directions>> {
+    let wanted = iteration
+        .threads
+        .saturating_mul(ITEMS_PER_THREAD)
+        .min(MAX_ITEMS);
+    let root = WorkItem {
+        board: root.clone(),
+        estimate: root_estimate,
+        path: vec![],
+    };
+    let mut layer_memory = root.memory();
+    let mut layer = vec![root];
+    while layer.len() < wanted {
+        let mut next_layer = Vec::new();
+        let mut next_memory = 0;
+        for item in &layer {
+            if item.board == *target {
+                return Err(item.path.clone());
+            }
+            result.nodes_expanded += 1;
+            let h = item.estimate.value;
+            result.lowest_h = Some(result.lowest_h.map_or(h, |lowest| lowest.min(h)));
+            let tick = result.nodes_expanded.is_multiple_of(TICK_NODES);
+            if let Some(stop) =
+                iteration.stop(result.nodes_expanded, layer_memory + next_memory, tick)
+            {
+                match stop {
+                    Stop::Limit(limit) => result.limit_reached = Some(limit),
+                    Stop::Cancelled => result.cancelled = true,
+                }
+                return Ok(vec![]);
+            }
+            for dir in Directions::ALL {
+                if !item.board.can_move(dir) || item.path.last() == Some(&dir.opposite()) {
+                    continue;
+                }
+                let mut board = item.board.clone();
+                let estimate = iteration
+                    .evaluator
+                    .make_move(&mut board, dir, item.estimate);
+                result.nodes_generated += 1;
+                let f_score = item.path.len() + 1 + estimate.value;
+                if f_score > iteration.bound {
+                    result.min_exceeded = result.min_exceeded.min(f_score);
+                    continue;
+                }
+                let mut path = item.path.clone();
+                path.push(dir);
+                let child = WorkItem {
+                    board,
+                    estimate,
+                    path,
+                };
+                next_memory += child.memory();
+                next_layer.push(child);
+            }
+        }
+        // the whole tree within the bound is already expanded
+        if next_layer.is_empty() {
+            return Ok(next_layer);
+        }
+        layer = next_layer;
+        layer_memory = next_memory;
+    }
+    Ok(layer)
+}
+
+impl<S: PuzzleState> Worker<'_, '_, S> {
+    fn run(&mut self, items: &[WorkItem<S>]) -> usize {
+        let mut min_exceeded = usize::MAX;
+        loop {
+            let index = self.shared.next_item.fetch_add(1, Ordering::Relaxed);
+            if index >= items.len() || self.shared.stop.load(Ordering::Relaxed) {
+                break;
+            }
+            let item = &items[index];
+            let mut board = item.board.clone();
+            let mut path = item.path.clone();
+            min_exceeded = min_exceeded.min(self.dfs(&mut board, item.estimate, &mut path));
+        }
+        self.flush();
+        min_exceeded
+    }
+
+    // Same search as Solver::dfs
+    fn dfs(&mut self, board: &mut S, estimate: Estimate, path: &mut Vec<Directions>) -> usize {
+        let iteration = self.shared.iteration;
+        let h = estimate.value;
+        let f_score = path.len() + h;
+        if f_score > iteration.bound {
+            return f_score;
+        }
+        if self.shared.stop.load(Ordering::Relaxed) {
+            return usize::MAX;
+        }
+        if *board == *self.target {
+            let mut solution = self.shared.solution.lock().unwrap();
+            if solution.is_none() {
+                *solution = Some(path.clone());
+            }
+            self.shared.stop.store(true, Ordering::Relaxed);
+            return f_score;
+        }
+
+        self.nodes_expanded += 1;
+        self.lowest_h = self.lowest_h.min(h);
+        if self.nodes_expanded.is_multiple_of(TICK_NODES) {
+            self.memory =
+                size_of::<S>() + board.heap_size() + path.capacity() * size_of::<Directions>();
+            if !self.flush() {
+                return usize::MAX;
+            }
+        }
+
+        let mut min_exceeded = usize::MAX;
+        for dir in Directions::ALL {
+            if !board.can_move(dir) || path.last() == Some(&dir.opposite()) {
+                continue;
+            }
+            let child = iteration.evaluator.make_move(board, dir, estimate);
+            self.nodes_generated += 1;
+            path.push(dir);
+            let child_min = self.dfs(board, child, path);
+            min_exceeded = min_exceeded.min(child_min);
+            path.pop();
+            board.make_move(dir.opposite());
+            if self.shared.stop.load(Ordering::Relaxed) {
+                break;
+            }
+        }
+        min_exceeded
+    }
+
+    // Adds the local counters to the shared ones and checks the limits, which are only
+    // approximate: every thread may expand up to TICK_NODES nodes more than the others know,
+    // and the memory counts every thread as using as much as this one
+    fn flush(&mut self) -> bool {
+        let shared = self.shared;
+        let iteration = shared.iteration;
+        let expanded = shared
+            .nodes_expanded
+            .fetch_add(self.nodes_expanded, Ordering::Relaxed)
+            + self.nodes_expanded;
+        shared
+            .nodes_generated
+            .fetch_add(self.nodes_generated, Ordering::Relaxed);
+        shared.lowest_h.fetch_min(self.lowest_h, Ordering::Relaxed);
+        self.nodes_expanded = 0;
+        self.nodes_generated = 0;
+
+        let memory = shared.items_memory + shared.threads * self.memory;
+        match iteration.stop(expanded, memory, true) {
+            Some(Stop::Limit(limit)) => {
+                shared.limit_reached.lock().unwrap().get_or_insert(limit);
+                shared.stop.store(true, Ordering::Relaxed);
+            }
+            Some(Stop::Cancelled) => {
+                shared.cancelled.store(true, Ordering::Relaxed);
+                shared.stop.store(true, Ordering::Relaxed);
+            }
+            None => {}
+        }
+        !shared.stop.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,1089 @@
+use crate::board::{Board, PuzzleState};
+use crate::goal::Goal;
+use crate::ranking::{self, permutations, rank, unrank_into};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
+
+// The 8-tile table of the 15-puzzle already takes 519MB
+const MAX_PATTERN_SIZE: usize = 8;
+// Cells are the bits of a u64 while building, and ranked as such
+const MAX_CELLS: usize = ranking::MAX_VALUES;
+const UNVISITED: u8 = u8::MAX;
+
+// Distances of one group of tiles to their goal positions, counting only the moves of these tiles
+pub struct Pattern {
+    tiles: Vec<usize>,
+    table: Vec<u8>,
+}
+
+// Additive disjoint pattern databases: the sum of the patterns never overestimates the distance
+pub struct PatternDatabase {
+    rows: usize,
+    cols: usize,
+    goal: Vec<usize>,
+    patterns: Vec<Pattern>,
+    // pattern of every tile and its index in the pattern, None for the blank
+    slots: Vec<Option<(u8, u8)>>,
+    reflection: Option<Reflection>,
+}
+
+// Square puzzles whose goal has the blank on the main diagonal are symmetric: transposing a
+// state and renaming its tiles after the goal gives a state at the same distance, so the
+// database also looked up for the reflected state gives a second admissible heuristic.
+struct Reflection {
+    // slot of the reflected tile, for every tile
+    slots: Vec<Option<(u8, u8)>>,
+    // transposed cell of every cell
+    cells: Vec<u8>,
+}
+
+/*
+File format, every integer is little-endian:
+    magic "NPDB", version (u16), rows (u16), cols (u16),
+    goal tiles in row-major order (rows x cols x u16),
+    number of patterns (u16), then for each pattern: number of tiles (u16), tiles (u16 each),
+    checksum (u64, FNV-1a of everything else in the file),
+    then the tables of the patterns, one byte per entry.
+*/
+const MAGIC: &[u8; 4] = b"NPDB";
+const VERSION: u16 = 2;
+
+#[derive(Debug)]
+pub enum PdbError {
+    Io(io::Error),
+    BadMagic,
+    UnsupportedVersion(u16),
+    SizeMismatch {
+        expected: (usize, usize),
+        found: (usize, usize),
+    },
+    GoalMismatch,
+    Corrupted(String),
+    // the database was built but could not be written to the cache directory
+    Save(io::Error),
+}
+
+impl fmt::Display for PdbError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PdbError::Io(e) => write!(f, "{e}"),
+            PdbError::BadMagic => write!(f, "not a pattern database file"),
+            PdbError::UnsupportedVersion(version) => {
+                write!(f, "unsupported pattern database version {version}")
+            }
+            PdbError::SizeMismatch { expected, found } => write!(
+                f,
+                "pattern database for {}x{} puzzles, expected {}x{}",
+                found.0, found.1, expected.0, expected.1
+            ),
+            PdbError::GoalMismatch => write!(f, "pattern database built for another goal"),
+            PdbError::Corrupted(reason) => write!(f, "corrupted pattern database: {reason}"),
+            PdbError::Save(e) => write!(f, "could not save the pattern database: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for PdbError {}
+
+impl From<io::Error> for PdbError {
+    fn from(e: io::Error) -> Self {
+        PdbError::Io(e)
+    }
+}
+
+impl PatternDatabase {
+    // Partition used for each size: 4-4 for the 3x3 puzzle, and 8-7 for the 4x4 puzzle with the
+    // top two rows and the bottom two, which the reflection turns into the left and right
+    // halves. Other puzzles up to 16 cells get groups of consecutive tiles of (almost) the same
+    // size.
+    pub fn default_partition(rows: usize, cols: usize) -> Option<Vec<Vec<usize>>> {
+        match (rows, cols) {
+            (2, 2) => Some(vec![vec![1, 2, 3]]),
+            (3, 3) => Some(vec![vec![1, 2, 3, 4], vec![5, 6, 7, 8]]),
+            (4, 4) => Some(vec![
+                vec![1, 2, 3, 4, 5, 6, 7, 8],
+                vec![9, 10, 11, 12, 13, 14, 15],
+            ]),
+            _ if rows * cols <= 16 => {
+                let tiles: Vec<usize> = (1..rows * cols).collect();
+                if tiles.len() <= MAX_PATTERN_SIZE {
+                    return Some(vec![tiles]);
+                }
+                let groups = tiles.len().div_ceil(6);
+                let size = tiles.len().div_ceil(groups);
+                Some(tiles.chunks(size).map(|group| group.to_vec()).collect())
+            }
+            _ => None,
+        }
+    }
+
+    pub fn build(target: &Board, partition: &[Vec<usize>]) -> Self {
+        let patterns = partition
+            .iter()
+            .map(|tiles| Pattern::build(target, tiles))
+            .collect();
+        PatternDatabase::new(
+            target.get_rows(),
+            target.get_cols(),
+            target.get_grid().iter().copied().collect(),
+            patterns,
+        )
+    }
+
+    fn new(rows: usize, cols: usize, goal: Vec<usize>, patterns: Vec<Pattern>) -> Self {
+        let cells = rows * cols;
+        let mut slots = vec![None; cells];
+        for (p, pattern) in patterns.iter().enumerate() {
+            for (i, &tile) in pattern.tiles.iter().enumerate() {
+                slots[tile] = Some((p as u8, i as u8));
+            }
+        }
+        let transpose = |cell: usize| (cell % cols) * rows + cell / cols;
+        let blank = goal.iter().position(|&tile| tile == 0).unwrap();
+        let reflection = (rows == cols && transpose(blank) == blank).then(|| {
+            let mut goal_cells = vec![0; cells];
+            for (cell, &tile) in goal.iter().enumerate() {
+                goal_cells[tile] = cell;
+            }
+            Reflection {
+                slots: (0..cells)
+                    .map(|tile| slots[goal[transpose(goal_cells[tile])]])
+                    .collect(),
+                cells: (0..cells).map(|cell| transpose(cell) as u8).collect(),
+            }
+        });
+        PatternDatabase {
+            rows,
+            cols,
+            goal,
+            patterns,
+            slots,
+            reflection,
+        }
+    }
+
+    // (rows, cols) of the puzzles this database was built for
+    pub fn get_size(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    pub fn save(&self, writer: impl Write) -> io::Result<()> {
+        let mut header = vec![];
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&VERSION.to_le_bytes());
+        header.extend_from_slice(&(self.rows as u16).to_le_bytes());
+        header.extend_from_slice(&(self.cols as u16).to_le_bytes());
+        for &tile in &self.goal {
+            header.extend_from_slice(&(tile as u16).to_le_bytes());
+        }
+        header.extend_from_slice(&(self.patterns.len() as u16).to_le_bytes());
+        for pattern in &self.patterns {
+            header.extend_from_slice(&(pattern.tiles.len() as u16).to_le_bytes());
+            for &tile in &pattern.tiles {
+                header.extend_from_slice(&(tile as u16).to_le_bytes());
+            }
+        }
+        let mut checksum = Fnv1a::new();
+        checksum.update(&header);
+        for pattern in &self.patterns {
+            checksum.update(&pattern.table);
+        }
+
+        let mut writer = BufWriter::new(writer);
+        writer.write_all(&header)?;
+        writer.write_all(&checksum.0.to_le_bytes())?;
+        for pattern in &self.patterns {
+            writer.write_all(&pattern.table)?;
+        }
+        writer.flush()
+    }
+
+    // Streams a database, which must have been built for the given goal
+    pub fn load(reader: impl Read, goal: &Board) -> Result<Self, PdbError> {
+        let mut reader = HashingReader {
+            inner: BufReader::new(reader),
+            checksum: Fnv1a::new(),
+        };
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(PdbError::BadMagic);
+        }
+        let version = reader.read_u16()?;
+        if version != VERSION {
+            return Err(PdbError::UnsupportedVersion(version));
+        }
+        let rows = reader.read_u16()? as usize;
+        let cols = reader.read_u16()? as usize;
+        let expected = (goal.get_rows(), goal.get_cols());
+        if (rows, cols) != expected {
+            return Err(PdbError::SizeMismatch {
+                expected,
+                found: (rows, cols),
+            });
+        }
+        let cells = rows * cols;
+        if cells > MAX_CELLS {
+            return Err(PdbError::Corrupted(format!(
+                "{cells} cells, at most {MAX_CELLS} are supported"
+            )));
+        }
+        let mut goal_tiles = Vec::with_capacity(cells);
+        for _ in 0..cells {
+            goal_tiles.push(reader.read_u16()? as usize);
+        }
+        if !goal_tiles.iter().eq(goal.get_grid().iter()) {
+            return Err(PdbError::GoalMismatch);
+        }
+
+        let nb_patterns = reader.read_u16()? as usize;
+        if nb_patterns >= cells {
+            return Err(PdbError::Corrupted(format!("{nb_patterns} patterns")));
+        }
+        let mut seen = vec![false; cells];
+        let mut partition = Vec::with_capacity(nb_patterns);
+        for _ in 0..nb_patterns {
+            let k = reader.read_u16()? as usize;
+            if k == 0 || k > MAX_PATTERN_SIZE {
+                return Err(PdbError::Corrupted(format!("pattern of {k} tiles")));
+            }
+            let mut tiles = Vec::with_capacity(k);
+            for _ in 0..k {
+                let tile = reader.read_u16()? as usize;
+                if tile == 0 || tile >= cells || seen[tile] {
+                    return Err(PdbError::Corrupted(format!("invalid tile {tile}")));
+                }
+                seen[tile] = true;
+                tiles.push(tile);
+            }
+            partition.push(tiles);
+        }
+
+        let expected_checksum = reader.read_u64_unhashed()?;
+        let mut patterns = Vec::with_capacity(nb_patterns);
+        for tiles in partition {
+            let size = permutations(cells, tiles.len())
+                .ok_or_else(|| PdbError::Corrupted(format!("pattern of {} tiles", tiles.len())))?;
+            // only as much memory as the file holds, whatever size the header claims
+            let mut table = Vec::new();
+            (&mut reader).take(size as u64).read_to_end(&mut table)?;
+            if table.len() != size {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+            patterns.push(Pattern { tiles, table });
+        }
+        if reader.read(&mut [0])? != 0 {
+            return Err(PdbError::Corrupted("trailing data".to_string()));
+        }
+        if reader.checksum.0 != expected_checksum {
+            return Err(PdbError::Corrupted("checksum mismatch".to_string()));
+        }
+
+        Ok(PatternDatabase::new(rows, cols, goal_tiles, patterns))
+    }
+
+    pub fn save_file(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.save(File::create(path)?)
+    }
+
+    pub fn load_file(path: impl AsRef<Path>, goal: &Board) -> Result<Self, PdbError> {
+        PatternDatabase::load(File::open(path)?, goal)
+    }
+
+    pub fn heuristic(&self, state: &impl PuzzleState) -> usize {
+        let (direct, reflected) = self.distances(state);
+        direct.max(reflected)
+    }
+
+    // Sums of the patterns for the state and for its reflection, 0 without reflection
+    pub fn distances(&self, state: &impl PuzzleState) -> (usize, usize) {
+        let mut cells = [[0; MAX_PATTERN_SIZE]; 2];
+        let (mut direct, mut reflected) = (0, 0);
+        for p in 0..self.patterns.len() {
+            self.pattern_cells(state, [p, p], &mut cells);
+            direct += self.lookup(p, &cells[0]);
+            if self.reflection.is_some() {
+                reflected += self.lookup(p, &cells[1]);
+            }
+        }
+        (direct, reflected)
+    }
+
+    // Changes of both sums when the tile moves from cell `from` to cell `to`, the state being
+    // taken before the move. Only the patterns holding the tile are looked up.
+    pub fn move_deltas(
+        &self,
+        state: &impl PuzzleState,
+        tile: usize,
+        from: usize,
+        to: usize,
+    ) -> (isize, isize) {
+        let Some((p, i)) = self.slots[tile] else {
+            return (0, 0);
+        };
+        let reflected_slot = self.reflection.as_ref().and_then(|r| r.slots[tile]);
+        let q = reflected_slot.map_or(p, |(q, _)| q);
+        let mut cells = [[0; MAX_PATTERN_SIZE]; 2];
+        self.pattern_cells(state, [p as usize, q as usize], &mut cells);
+
+        let delta = |p: u8, i: u8, cells: &mut [u8; MAX_PATTERN_SIZE], to: usize| {
+            let before = self.lookup(p as usize, cells) as isize;
+            cells[i as usize] = to as u8;
+            self.lookup(p as usize, cells) as isize - before
+        };
+        let direct = delta(p, i, &mut cells[0], to);
+        let reflected = match (&self.reflection, reflected_slot) {
+            (Some(reflection), Some((q, j))) => {
+                debug_assert_eq!(cells[1][j as usize], reflection.cells[from]);
+                delta(q, j, &mut cells[1], reflection.cells[to] as usize)
+            }
+            _ => 0,
+        };
+        (direct, reflected)
+    }
+
+    // Cells of the tiles of pattern patterns[0] in the state, and of pattern patterns[1] in the
+    // reflected state, without allocating
+    fn pattern_cells(
+        &self,
+        state: &impl PuzzleState,
+        patterns: [usize; 2],
+        cells: &mut [[u8; MAX_PATTERN_SIZE]; 2],
+    ) {
+        let cols = state.get_cols();
+        for row in 0..state.get_rows() {
+            for col in 0..cols {
+                let tile = state.tile(row, col);
+                let cell = row * cols + col;
+                if let Some((p, i)) = self.slots[tile]
+                    && p as usize == patterns[0]
+                {
+                    cells[0][i as usize] = cell as u8;
+                }
+                if let Some(reflection) = &self.reflection
+                    && let Some((q, j)) = reflection.slots[tile]
+                    && q as usize == patterns[1]
+                {
+                    cells[1][j as usize] = reflection.cells[cell];
+                }
+            }
+        }
+    }
+
+    // Distance of pattern p with its tiles at these cells
+    fn lookup(&self, p: usize, cells: &[u8; MAX_PATTERN_SIZE]) -> usize {
+        let pattern = &self.patterns[p];
+        pattern.table[rank(&cells[..pattern.tiles.len()], self.goal.len())] as usize
+    }
+}
+
+impl Pattern {
+    // BFS backwards from the target over the positions of the pattern tiles, one distance at a
+    // time. Moving the blank over another tile is free, so a state of the BFS is the positions
+    // of the pattern tiles and the whole region of free cells the blank can reach without
+    // moving them, and the table gets the first distance at which the positions are reached.
+    // Instead of the states, only the cells of the regions already reached are kept for every
+    // position, in the smallest integer holding a bit per cell.
+    fn build(target: &Board, tiles: &[usize]) -> Self {
+        if target.get_rows() * target.get_cols() <= 16 {
+            Pattern::build_with::<u16>(target, tiles)
+        } else {
+            Pattern::build_with::<u64>(target, tiles)
+        }
+    }
+
+    fn build_with<M: CellSet>(target: &Board, tiles: &[usize]) -> Self {
+        assert!(
+            !tiles.is_empty() && tiles.len() <= MAX_PATTERN_SIZE,
+            "a pattern must contain between 1 and {MAX_PATTERN_SIZE} tiles"
+        );
+        let (rows, cols) = (target.get_rows(), target.get_cols());
+        let cells = rows * cols;
+        assert!(
+            cells <= MAX_CELLS,
+            "puzzles are limited to {MAX_CELLS} cells"
+        );
+        let k = tiles.len();
+        let size = permutations(cells, k).expect("pattern too large");
+        let grid = CellGrid::new(rows, cols);
+        let target_positions = tile_positions(target);
+
+        let mut start = [0u8; MAX_PATTERN_SIZE];
+        for (i, &tile) in tiles.iter().enumerate() {
+            start[i] = target_positions[tile] as u8;
+        }
+        let start_index = rank(&start[..k], cells);
+        let start_region = grid.region(target_positions[0], grid.free(&start[..k]));
+
+        let mut table = vec![UNVISITED; size];
+        table[start_index] = 0;
+        // for every position, the cells of the regions reached so far, then of the ones reached
+        // at the current distance and at the next one, which take turns. They are kept together
+        // so that a successor is a single memory access.
+        let mut regions = vec![[M::default(); 3]; size];
+        regions[start_index] = [
+            M::from_bits(start_region),
+            M::from_bits(start_region),
+            M::default(),
+        ];
+        // weight of the Lehmer digit of every slot in the index
+        let weights: Vec<usize> = (0..k)
+            .map(|j| permutations(cells - j - 1, k - j - 1).unwrap())
+            .collect();
+        let mut distance = 0;
+        let mut expanded = true;
+        while expanded {
+            expanded = false;
+            let (current, next) = if distance % 2 == 0 { (1, 2) } else { (2, 1) };
+            // the positions are expanded in the order of their index, so that the successors
+            // moving the same tile are looked up in order too
+            for index in 0..size {
+                let region = regions[index][current].bits();
+                if region == 0 {
+                    continue;
+                }
+                expanded = true;
+                regions[index][current] = M::default();
+                let mut positions = [0u8; MAX_PATTERN_SIZE];
+                unrank_into(index, cells, &mut positions[..k]);
+                let free = grid.free(&positions[..k]);
+                let mut slots = [0u8; MAX_CELLS];
+                for (i, &cell) in positions[..k].iter().enumerate() {
+                    slots[cell as usize] = i as u8;
+                }
+                // cells of the tiles of the previous slots
+                let mut earlier = 0;
+                for (i, &cell) in positions[..k].iter().enumerate() {
+                    // the tile goes to any cell next to it in a region of the blank, and the
+                    // blank takes its place
+                    let cell = cell as usize;
+                    let tiles = Move {
+                        earlier,
+                        later: grid.all & !free & !earlier & !(1 << cell),
+                        slots: &slots,
+                        weights: &weights,
+                    };
+                    let mut targets = grid.neighbors[cell] & region;
+                    while targets != 0 {
+                        let to = targets.trailing_zeros() as usize;
+                        targets &= targets - 1;
+                        let next_index = tiles.next_index(index, i, cell, to);
+                        let entry = &mut regions[next_index];
+                        let next_reached = entry[0].bits();
+                        if next_reached & 1 << cell != 0 {
+                            continue;
+                        }
+                        if next_reached == 0 {
+                            table[next_index] = distance + 1;
+                        }
+                        let next_region = grid.region(cell, free & !(1 << to) | 1 << cell);
+                        entry[0] = M::from_bits(next_reached | next_region);
+                        entry[next] = M::from_bits(entry[next].bits() | next_region);
+                    }
+                    earlier |= 1 << cell;
+                }
+            }
+            distance += 1;
+        }
+
+        Pattern {
+            tiles: tiles.to_vec(),
+            table,
+        }
+    }
+}
+
+// Set of cells with a bit per cell, u16 for the puzzles up to 16 cells to halve the memory of
+// the BFS
+trait CellSet: Copy + Default {
+    fn from_bits(bits: u64) -> Self;
+    fn bits(self) -> u64;
+}
+
+impl CellSet for u16 {
+    fn from_bits(bits: u64) -> Self {
+        bits as u16
+    }
+
+    fn bits(self) -> u64 {
+        self as u64
+    }
+}
+
+impl CellSet for u64 {
+    fn from_bits(bits: u64) -> Self {
+        bits
+    }
+
+    fn bits(self) -> u64 {
+        self
+    }
+}
+
+// The tiles of a position around the one of slot i, to get the index of the position once
+// that tile moved without ranking it again
+struct Move<'a> {
+    // cells of the tiles of the slots before i, and after it
+    earlier: u64,
+    later: u64,
+    // slot of the tile of every cell
+    slots: &'a [u8],
+    // weight of the Lehmer digit of every slot
+    weights: &'a [usize],
+}
+
+impl Move<'_> {
+    // Only the digits of the tiles between both cells change: the one of the moved tile loses
+    // the earlier tiles in between, and the later tiles in between now count it or stop to.
+    fn next_index(&self, index: usize, i: usize, from: usize, to: usize) -> usize {
+        let (low, high) = (from.min(to), from.max(to));
+        let between = (1u64 << high) - (2u64 << low);
+        let earlier = (self.earlier & between).count_ones() as usize;
+        let mut later = self.later & between;
+        let mut later_weights = 0;
+        while later != 0 {
+            later_weights += self.weights[self.slots[later.trailing_zeros() as usize] as usize];
+            later &= later - 1;
+        }
+        if to > from {
+            index + (to - from - earlier) * self.weights[i] + later_weights
+        } else {
+            index - (from - to - earlier) * self.weights[i] - later_weights
+        }
+    }
+}
+
+// Cells of a board as the bits of a u64
+struct CellGrid {
+    cols: usize,
+    all: u64,
+    // cells next to every cell
+    neighbors: Vec<u64>,
+    // cells which are not in the first or last column
+    not_first_col: u64,
+    not_last_col: u64,
+}
+
+impl CellGrid {
+    fn new(rows: usize, cols: usize) -> Self {
+        let cells = rows * cols;
+        let all = u64::MAX.checked_shr((64 - cells) as u32).unwrap_or(0);
+        let column = |col: usize| (0..rows).fold(0u64, |mask, row| mask | 1 << (row * cols + col));
+        let neighbors = (0..cells)
+            .map(|cell| {
+                let (row, col) = (cell / cols, cell % cols);
+                let mut mask = 0u64;
+                if row > 0 {
+                    mask |= 1 << (cell - cols);
+                }
+                if row + 1 < rows {
+                    mask |= 1 << (cell + cols);
+                }
+                if col > 0 {
+                    mask |= 1 << (cell - 1);
+                }
+                if col + 1 < cols {
+                    mask |= 1 << (cell + 1);
+                }
+                mask
+            })
+            .collect();
+        CellGrid {
+            cols,
+            all,
+            neighbors,
+            not_first_col: all & !column(0),
+            not_last_col: all & !column(cols - 1),
+        }
+    }
+
+    // Cells not holding one of these positions
+    fn free(&self, positions: &[u8]) -> u64 {
+        positions
+            .iter()
+            .fold(self.all, |free, &cell| free & !(1 << cell))
+    }
+
+    // Cells reached from this one through free cells
+    fn region(&self, cell: usize, free: u64) -> u64 {
+        let mut region = 1 << cell;
+        loop {
+            let grown = (region
+                | (region << 1 & self.not_first_col)
+                | (region >> 1 & self.not_last_col)
+                | region << self.cols
+                | region >> self.cols)
+                & free;
+            if grown == region {
+                return region;
+            }
+            region = grown;
+        }
+    }
+}
+
+// Position (row * cols + col) of every tile of the board
+fn tile_positions(state: &impl PuzzleState) -> Vec<usize> {
+    let (rows, cols) = (state.get_rows(), state.get_cols());
+    let mut positions = vec![0; rows * cols];
+    for row in 0..rows {
+        for col in 0..cols {
+            positions[state.tile(row, col)] = row * cols + col;
+        }
+    }
+    positions
+}
+
+// 64-bit FNV-1a hash, used as the checksum of the database files
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Fnv1a(0xcbf29ce484222325)
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+struct HashingReader<R> {
+    inner: R,
+    checksum: Fnv1a,
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.checksum.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+impl<R: Read> HashingReader<R> {
+    fn read_u16(&mut self) -> io::Result<u16> {
+        let mut bytes = [0; 2];
+        self.read_exact(&mut bytes)?;
+        Ok(u16::from_le_bytes(bytes))
+    }
+
+    // the checksum itself is not part of the hashed data
+    fn read_u64_unhashed(&mut self) -> io::Result<u64> {
+        let mut bytes = [0; 8];
+        self.inner.read_exact(&mut bytes)?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+}
+
+// Databases are identified by the tiles of their goal, in row-major order
+static DATABASES: OnceLock<RwLock<HashMap<Vec<usize>, Arc<PatternDatabase>>>> = OnceLock::new();
+static CACHE_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+fn databases() -> &'static RwLock<HashMap<Vec<usize>, Arc<PatternDatabase>>> {
+    DATABASES.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+fn goal_tiles(goal: &Goal) -> Vec<usize> {
+    goal.get_board().get_grid().iter().copied().collect()
+}
+
+// Databases are then read from (or written to) this directory instead of being rebuilt every time
+pub fn set_cache_dir(dir: impl Into<PathBuf>) {
+    *CACHE_DIR.write().unwrap() = Some(dir.into());
+}
+
+// Other goals than the standard one get a hash of their tiles in the file name
+pub fn cache_file(dir: &Path, goal: &Goal) -> PathBuf {
+    let (rows, cols) = goal.get_size();
+    if *goal == Goal::standard(rows, cols) {
+        return dir.join(format!("pdb-{rows}x{cols}.bin"));
+    }
+    let mut hash = Fnv1a::new();
+    for tile in goal_tiles(goal) {
+        hash.update(&(tile as u16).to_le_bytes());
+    }
+    dir.join(format!("pdb-{rows}x{cols}-{:016x}.bin", hash.0))
+}
+
+// Makes the heuristic use this database for its goal
+pub fn register(database: PatternDatabase) {
+    databases()
+        .write()
+        .unwrap()
+        .insert(database.goal.clone(), Arc::new(database));
+}
+
+// Pattern database of the standard goal for this size
+pub fn for_size(rows: usize, cols: usize) -> Option<Arc<PatternDatabase>> {
+    for_goal(&Goal::standard(rows, cols))
+}
+
+// Pattern database of the goal, loaded or built on first use and shared by every search.
+// The searches can't report the errors of the cache directory, they only get a database when
+// load() succeeded or at least built it.
+pub fn for_goal(goal: &Goal) -> Option<Arc<PatternDatabase>> {
+    match load(goal) {
+        Ok(database) => database,
+        Err(_) => databases().read().unwrap().get(&goal_tiles(goal)).cloned(),
+    }
+}
+
+// Loads the pattern database of the goal from the cache directory, or builds it when there is
+// no file for it yet. A file which can't be read is reported and left alone. When the built
+// database can't be saved, it is still registered for the searches and PdbError::Save is
+// returned. None when there is no partition for this size.
+pub fn load(goal: &Goal) -> Result<Option<Arc<PatternDatabase>>, PdbError> {
+    let dir = CACHE_DIR.read().unwrap().clone();
+    load_in(dir.as_deref(), goal)
+}
+
+// The lock is only taken to insert the database, so that the searches of the other goals go on
+// while it is read or built
+fn load_in(dir: Option<&Path>, goal: &Goal) -> Result<Option<Arc<PatternDatabase>>, PdbError> {
+    let tiles = goal_tiles(goal);
+    if let Some(database) = databases().read().unwrap().get(&tiles) {
+        return Ok(Some(database.clone()));
+    }
+    let (rows, cols) = goal.get_size();
+    let Some(partition) = PatternDatabase::default_partition(rows, cols) else {
+        return Ok(None);
+    };
+    let board = goal.get_board();
+    let path = dir.map(|dir| cache_file(dir, goal));
+    if let Some(path) = &path {
+        match PatternDatabase::load_file(path, board) {
+            Ok(database) => return Ok(Some(insert(tiles, database).0)),
+            Err(PdbError::Io(e)) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    let (database, inserted) = insert(tiles, PatternDatabase::build(board, &partition));
+    if let (true, Some(dir), Some(path)) = (inserted, dir, path) {
+        fs::create_dir_all(dir)
+            .and_then(|_| database.save_file(path))
+            .map_err(PdbError::Save)?;
+    }
+    Ok(Some(database))
+}
+
+// Registers the database unless another thread registered one for the goal in the meantime.
+// Returns the registered database, and whether it is this one.
+fn insert(tiles: Vec<usize>, database: PatternDatabase) -> (Arc<PatternDatabase>, bool) {
+    let mut databases = databases().write().unwrap();
+    if let Some(other) = databases.get(&tiles) {
+        return (other.clone(), false);
+    }
+    let database = Arc::new(database);
+    databases.insert(tiles, database.clone());
+    (database, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Heuristics;
+    use crate::generator::{Generator, Rng};
+    use crate::ranking;
+    use crate::state_space::StateSpace;
+
+    #[test]
+    fn solved_board_test() {
+        let database = for_size(3, 3).unwrap();
+        assert_eq!(database.heuristic(&Board::solved(3, 3)), 0);
+    }
+
+    #[test]
+    fn heuristic_test() {
+        /*
+        3
+        8 6 7
+        2 5 4
+        3 0 1
+        */
+        let board = Board::load_from_str(3, 3, "8 6 7\n2 5 4\n3 0 1").unwrap();
+        let database = for_size(3, 3).unwrap();
+        let pdb = database.heuristic(&board);
+        // the pattern database dominates Manhattan distance, and this board needs 31 moves
+        let goal = Goal::standard(3, 3);
+        assert!(pdb >= board.heuristic(Heuristics::MANHATTAN, &goal));
+        assert!(pdb <= 31);
+        assert_eq!(board.heuristic(Heuristics::PATTERNDB, &goal), pdb);
+    }
+
+    #[test]
+    fn reflection_test() {
+        /*
+        1 2 3      1 2 0
+        4 5 6      4 5 3
+        0 7 8      7 8 6
+        the second one is the first one transposed, with its tiles renamed after the goal
+        */
+        let database = for_size(3, 3).unwrap();
+        let board = Board::load_from_str(3, 3, "1 2 3\n4 5 6\n0 7 8").unwrap();
+        let reflected = Board::load_from_str(3, 3, "1 2 0\n4 5 3\n7 8 6").unwrap();
+        let (direct, reflected_distance) = database.distances(&board);
+        assert_eq!(database.distances(&reflected), (reflected_distance, direct));
+        assert_eq!(database.heuristic(&board), 2);
+
+        // no reflection when the blank of the goal is off the diagonal
+        let goal = Board::load_from_str(3, 3, "1 2 3\n4 5 6\n7 0 8").unwrap();
+        let database = PatternDatabase::build(&goal, &[vec![1, 2, 3, 4], vec![5, 6, 7, 8]]);
+        assert!(database.reflection.is_none());
+        assert_eq!(database.distances(&board).1, 0);
+    }
+
+    #[test]
+    fn move_deltas_test() {
+        for goal in [
+            Goal::standard(3, 3),
+            Goal::snail(3, 3),
+            Goal::standard(2, 4),
+        ] {
+            let database = for_goal(&goal).unwrap();
+            let mut board = Generator::new(goal.clone(), 5).random_board();
+            let mut rng = Rng::new(5);
+            for _ in 0..300 {
+                let directions = board.next_directions();
+                let dir = directions[rng.below(directions.len())];
+                let (direct, reflected) = database.distances(&board);
+                let (row, col) = board.get_pos_0();
+                let cols = board.get_cols();
+                let mut next = board.clone();
+                next.make_move(dir);
+                let (next_row, next_col) = next.get_pos_0();
+                let deltas = database.move_deltas(
+                    &board,
+                    board.tile(next_row, next_col),
+                    next_row * cols + next_col,
+                    row * cols + col,
+                );
+                let (next_direct, next_reflected) = database.distances(&next);
+                assert_eq!(
+                    deltas,
+                    (
+                        next_direct as isize - direct as isize,
+                        next_reflected as isize - reflected as isize
+                    )
+                );
+                board = next;
+            }
+        }
+    }
+
+    #[test]
+    fn admissible_test() {
+        // never above the exact distance of the 3x3 boards
+        let goal = Goal::standard(3, 3);
+        let database = for_goal(&goal).unwrap();
+        let space = StateSpace::explore(goal).unwrap();
+        for index in (0..ranking::permutations(9, 9).unwrap()).step_by(5) {
+            let board = Board::unrank(3, 3, index).unwrap();
+            if let Some(distance) = space.distance(&board) {
+                assert!(database.heuristic(&board) <= distance, "{board}");
+            }
+        }
+    }
+
+    #[test]
+    fn next_index_test() {
+        // every move of every placement of 3 tiles of the 3x3 puzzle gets the index of the
+        // positions after it
+        let (cells, k) = (9, 3);
+        let grid = CellGrid::new(3, 3);
+        let weights: Vec<usize> = (0..k)
+            .map(|j| ranking::permutations(cells - j - 1, k - j - 1).unwrap())
+            .collect();
+        for index in 0..ranking::permutations(cells, k).unwrap() {
+            let mut positions = [0u8; 3];
+            unrank_into(index, cells, &mut positions);
+            let free = grid.free(&positions);
+            let mut slots = [0u8; MAX_CELLS];
+            for (i, &cell) in positions.iter().enumerate() {
+                slots[cell as usize] = i as u8;
+            }
+            let mut earlier = 0;
+            for (i, &cell) in positions.iter().enumerate() {
+                let cell = cell as usize;
+                let tiles = Move {
+                    earlier,
+                    later: grid.all & !free & !earlier & !(1 << cell),
+                    slots: &slots,
+                    weights: &weights,
+                };
+                for to in (0..cells).filter(|&to| grid.neighbors[cell] & free & 1 << to != 0) {
+                    let mut next = positions;
+                    next[i] = to as u8;
+                    assert_eq!(tiles.next_index(index, i, cell, to), rank(&next, cells));
+                }
+                earlier |= 1 << cell;
+            }
+        }
+    }
+
+    #[test]
+    fn unsupported_size_test() {
+        assert!(for_size(5, 5).is_none());
+        assert!(for_size(4, 5).is_none());
+    }
+
+    #[test]
+    fn rectangular_test() {
+        let partition = PatternDatabase::default_partition(3, 5).unwrap();
+        let sizes: Vec<usize> = partition.iter().map(|tiles| tiles.len()).collect();
+        assert_eq!(sizes, vec![5, 5, 4]);
+        assert_eq!(PatternDatabase::default_partition(2, 4).unwrap().len(), 1);
+
+        /*
+        2 3
+        4 1 2
+        0 5 3
+        */
+        let board = Board::load_from_str(2, 3, "4 1 2\n0 5 3").unwrap();
+        let database = for_size(2, 3).unwrap();
+        assert_eq!(database.get_size(), (2, 3));
+        assert_eq!(database.heuristic(&Board::solved(2, 3)), 0);
+        // a single pattern holding every tile gives the exact distance
+        assert_eq!(database.heuristic(&board), 4);
+    }
+
+    #[test]
+    fn other_goal_test() {
+        let goal = Goal::snail(3, 3);
+        let database = for_goal(&goal).unwrap();
+        assert!(!Arc::ptr_eq(&database, &for_size(3, 3).unwrap()));
+        assert_eq!(database.heuristic(goal.get_board()), 0);
+        assert!(database.heuristic(&Board::solved(3, 3)) > 0);
+        assert_ne!(
+            cache_file(Path::new("pdbs"), &goal),
+            cache_file(Path::new("pdbs"), &Goal::standard(3, 3))
+        );
+        assert_eq!(
+            cache_file(Path::new("pdbs"), &Goal::standard(4, 4)),
+            Path::new("pdbs/pdb-4x4.bin")
+        );
+    }
+
+    #[test]
+    fn cache_dir_test() {
+        let dir = std::env::temp_dir().join(format!("pdb-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        // a file which can't be read is reported and kept
+        let goal: Goal = "2\n3 1\n2 0".parse().unwrap();
+        let path = cache_file(&dir, &goal);
+        fs::write(&path, b"PDB?").unwrap();
+        assert!(matches!(
+            load_in(Some(&dir), &goal),
+            Err(PdbError::BadMagic)
+        ));
+        assert_eq!(fs::read(&path).unwrap(), b"PDB?");
+
+        // a missing one is built and saved
+        fs::remove_file(&path).unwrap();
+        let database = load_in(Some(&dir), &goal).unwrap().unwrap();
+        assert!(PatternDatabase::load_file(&path, goal.get_board()).is_ok());
+        assert!(Arc::ptr_eq(&database, &for_goal(&goal).unwrap()));
+
+        // a database which can't be saved, here through a link to a missing directory, is
+        // still used
+        #[cfg(unix)]
+        {
+            let goal: Goal = "2\n1 3\n2 0".parse().unwrap();
+            let missing = dir.join("missing").join("pdb.bin");
+            std::os::unix::fs::symlink(missing, cache_file(&dir, &goal)).unwrap();
+            assert!(matches!(load_in(Some(&dir), &goal), Err(PdbError::Save(_))));
+            assert_eq!(for_goal(&goal).unwrap().heuristic(goal.get_board()), 0);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn save_load_test() {
+        let goal = Board::solved(3, 3);
+        let database = PatternDatabase::build(&goal, &[vec![1, 2, 3], vec![4, 5, 6, 7, 8]]);
+        let mut bytes = vec![];
+        database.save(&mut bytes).unwrap();
+
+        let loaded = PatternDatabase::load(&bytes[..], &goal).unwrap();
+        assert_eq!(loaded.get_size(), (3, 3));
+        assert_eq!(loaded.patterns.len(), 2);
+        for (a, b) in loaded.patterns.iter().zip(&database.patterns) {
+            assert_eq!(a.tiles, b.tiles);
+            assert_eq!(a.table, b.table);
+        }
+    }
+
+    #[test]
+    fn load_errors_test() {
+        let goal = Board::solved(2, 2);
+        let database = PatternDatabase::build(&goal, &[vec![1, 2, 3]]);
+        let mut bytes = vec![];
+        database.save(&mut bytes).unwrap();
+
+        assert!(matches!(
+            PatternDatabase::load(&bytes[..], &Board::solved(3, 3)),
+            Err(PdbError::SizeMismatch {
+                expected: (3, 3),
+                found: (2, 2)
+            })
+        ));
+        let other_goal = Board::load_from_str(2, 2, "0 1\n2 3").unwrap();
+        assert!(matches!(
+            PatternDatabase::load(&bytes[..], &other_goal),
+            Err(PdbError::GoalMismatch)
+        ));
+        assert!(matches!(
+            PatternDatabase::load(&b"PDB?"[..], &goal),
+            Err(PdbError::BadMagic)
+        ));
+
+        let mut corrupted = bytes.clone();
+        *corrupted.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            PatternDatabase::load(&corrupted[..], &goal),
+            Err(PdbError::Corrupted(_))
+        ));
+        assert!(matches!(
+            PatternDatabase::load(&bytes[..bytes.len() - 1], &goal),
+            Err(PdbError::Io(_))
+        ));
+
+        // the header of an 8x8 database with a pattern of 7 tiles but without its tables, which
+        // would take terabytes
+        let goal = Board::solved(8, 8);
+        let mut header = MAGIC.to_vec();
+        for value in [VERSION, 8, 8] {
+            header.extend(value.to_le_bytes());
+        }
+        for &tile in goal.get_grid().iter() {
+            header.extend((tile as u16).to_le_bytes());
+        }
+        for value in [1u16, 7, 1, 2, 3, 4, 5, 6, 7] {
+            header.extend(value.to_le_bytes());
+        }
+        header.extend(0u64.to_le_bytes());
+        assert!(matches!(
+            PatternDatabase::load(&header[..], &goal),
+            Err(PdbError::Io(_))
+        ));
+
+        // boards of more than 64 cells can't be ranked
+        let mut header = MAGIC.to_vec();
+        for value in [VERSION, 9, 9] {
+            header.extend(value.to_le_bytes());
+        }
+        assert!(matches!(
+            PatternDatabase::load(&header[..], &Board::solved(9, 9)),
+            Err(PdbError::Corrupted(_))
+        ));
+    }
+}
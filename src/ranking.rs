@@ -0,0 +1,118 @@
+// Dense indexes of partial permutations: k distinct values below n, in order, are numbered
+// from 0 to permutations(n, k) - 1 with their Lehmer code, where every value counts the
+// smaller values which are not taken yet by the previous ones
+
+// Number of ways to take k distinct values below n, in order, None when k is larger than n or
+// the number doesn't fit in a usize
+pub fn permutations(n: usize, k: usize) -> Option<usize> {
+    if k > n {
+        return None;
+    }
+    (n - k + 1..=n).try_fold(1usize, |product, value| product.checked_mul(value))
+}
+
+// The values are kept in the bits of a u64
+pub const MAX_VALUES: usize = 64;
+
+// Index of the distinct values below n, between 0 and permutations(n, items.len()), which must
+// fit in a usize. Linear in the number of items, n being at most MAX_VALUES.
+pub fn rank<T: Copy + Into<usize>>(items: &[T], n: usize) -> usize {
+    assert!(n <= MAX_VALUES, "{n} values can't be ranked");
+    // bit i is set when value i is already taken by a previous item
+    let mut taken: u64 = 0;
+    let mut index = 0;
+    for (j, &item) in items.iter().enumerate() {
+        let value = item.into();
+        let smaller = (taken & ((1 << value) - 1)).count_ones() as usize;
+        index = index * (n - j) + value - smaller;
+        taken |= 1 << value;
+    }
+    index
+}
+
+// The k distinct values below n having this index, n being at most MAX_VALUES
+pub fn unrank(index: usize, n: usize, k: usize) -> Vec<usize> {
+    let mut items = vec![0; k];
+    unrank_into(index, n, &mut items);
+    items.into_iter().map(usize::from).collect()
+}
+
+// Same as unrank, writing the values into items without allocating
+pub fn unrank_into(mut index: usize, n: usize, items: &mut [u8]) {
+    assert!(n <= MAX_VALUES, "{n} values can't be unranked");
+    // digits of the Lehmer code, the last one being the least significant
+    for j in (0..items.len()).rev() {
+        items[j] = (index % (n - j)) as u8;
+        index /= n - j;
+    }
+    // bit i is set while value i is not taken yet
+    let mut free = u64::MAX.checked_shr((MAX_VALUES - n) as u32).unwrap_or(0);
+    for item in items {
+        let value = select(free, *item as usize);
+        free &= !(1 << value);
+        *item = value as u8;
+    }
+}
+
+// Position of the set bit having this many set bits below it
+fn select(mut bits: u64, nth: usize) -> usize {
+    for _ in 0..nth {
+        bits &= bits - 1;
+    }
+    bits.trailing_zeros() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rank_test() {
+        let cells = 4;
+        let mut seen = vec![false; permutations(cells, 2).unwrap()];
+        for a in 0..4u8 {
+            for b in (0..4u8).filter(|&b| b != a) {
+                let index = rank(&[a, b], cells);
+                assert!(!seen[index]);
+                seen[index] = true;
+            }
+        }
+        assert!(seen.iter().all(|&s| s));
+    }
+
+    #[test]
+    fn unrank_test() {
+        for (n, k) in [(4, 4), (5, 2), (9, 3), (16, 1), (64, 2)] {
+            for index in 0..permutations(n, k).unwrap().min(5000) {
+                let items = unrank(index, n, k);
+                assert_eq!(items.len(), k);
+                assert_eq!(rank(&items, n), index);
+            }
+        }
+        assert_eq!(unrank(0, 4, 4), vec![0, 1, 2, 3]);
+        assert_eq!(unrank(23, 4, 4), vec![3, 2, 1, 0]);
+        assert_eq!(rank(&[63usize, 62], 64), permutations(64, 2).unwrap() - 1);
+        assert_eq!(
+            unrank(permutations(64, 3).unwrap() - 1, 64, 3),
+            vec![63, 62, 61]
+        );
+    }
+
+    #[test]
+    fn permutations_test() {
+        assert_eq!(permutations(4, 0), Some(1));
+        assert_eq!(permutations(4, 2), Some(12));
+        assert_eq!(permutations(20, 20), Some(2432902008176640000));
+        assert_eq!(permutations(21, 21), None);
+        assert_eq!(permutations(25, 25), None);
+        assert_eq!(permutations(3, 4), None);
+    }
+
+    #[test]
+    fn select_test() {
+        assert_eq!(select(0b1011, 0), 0);
+        assert_eq!(select(0b1011, 2), 3);
+        assert_eq!(select(u64::MAX, 63), 63);
+        assert_eq!(select(1 << 63 | 1 << 40, 1), 63);
+    }
+}
@@ -0,0 +1,169 @@
+use crate::board::{Board, ParseError};
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+// Reads the puzzles of a file holding any number of them, one at a time. Every puzzle is
+// either a block of lines as in a puzzle file (the size, then the rows), blocks being separated
+// by blank lines, or a single line "n: t1 t2 ..." ("rows cols: t1 t2 ..." for a rectangular
+// one) with the tiles in row-major order. Both forms can be mixed in the same file.
+// A puzzle which can't be parsed is reported, and the reading goes on with the next one.
+pub struct PuzzleReader<R> {
+    lines: io::Lines<R>,
+    // number of the last line read
+    line: usize,
+    done: bool,
+}
+
+impl<R: BufRead> PuzzleReader<R> {
+    pub fn new(reader: R) -> Self {
+        PuzzleReader {
+            lines: reader.lines(),
+            line: 0,
+            done: false,
+        }
+    }
+
+    fn next_line(&mut self) -> Option<Result<String, ParseError>> {
+        if self.done {
+            return None;
+        }
+        self.line += 1;
+        match self.lines.next() {
+            Some(Ok(text)) => Some(Ok(text)),
+            Some(Err(e)) => {
+                self.done = true;
+                Some(Err(ParseError::Read {
+                    line: self.line,
+                    message: e.to_string(),
+                }))
+            }
+            None => {
+                self.done = true;
+                None
+            }
+        }
+    }
+}
+
+impl PuzzleReader<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(PuzzleReader::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl<R: BufRead> Iterator for PuzzleReader<R> {
+    type Item = Result<Board, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = loop {
+            match self.next_line()? {
+                Ok(text) if text.trim().is_empty() => continue,
+                Ok(text) => break text,
+                Err(e) => return Some(Err(e)),
+            }
+        };
+        let first_line = self.line;
+        if first.contains(':') {
+            return Some(Board::parse_line(first_line, &first));
+        }
+        let mut block = vec![(first_line, first)];
+        while let Some(text) = self.next_line() {
+            match text {
+                Ok(text) if text.trim().is_empty() => break,
+                Ok(text) => block.push((self.line, text)),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        Some(Board::parse_lines(
+            block.iter().map(|(line, text)| (*line, text.as_str())),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::PuzzleState;
+    use grid::grid;
+
+    fn read(puzzles_str: &str) -> Vec<Result<Board, ParseError>> {
+        PuzzleReader::new(puzzles_str.as_bytes()).collect()
+    }
+
+    #[test]
+    fn blocks_test() {
+        let boards = read("\n3\n0 1 3\n4 2 5\n7 8 6\n\n\n2 3\n1 2 3\n4 0 5\n");
+        assert_eq!(boards.len(), 2);
+        let board = boards[0].as_ref().unwrap();
+        assert_eq!(*board.get_grid(), grid![[0, 1, 3][4, 2, 5][7, 8, 6]]);
+        let board = boards[1].as_ref().unwrap();
+        assert_eq!((board.get_rows(), board.get_cols()), (2, 3));
+        assert_eq!(board.get_pos_0(), (1, 1));
+    }
+
+    #[test]
+    fn one_line_test() {
+        let boards = read("3: 0 1 3 4 2 5 7 8 6\n2 3: 1 2 3 4 0 5\n\n2\n1 2\n3 0\n\n4: 1 2 3\n");
+        assert_eq!(boards.len(), 4);
+        assert_eq!(boards[0], "3\n0 1 3\n4 2 5\n7 8 6".parse());
+        assert_eq!(boards[1].as_ref().unwrap().get_pos_0(), (1, 1));
+        assert_eq!(boards[2], "2\n1 2\n3 0".parse());
+        assert_eq!(
+            boards[3],
+            Err(ParseError::WrongRowWidth {
+                line: 8,
+                expected: 16,
+                found: 3
+            })
+        );
+    }
+
+    #[test]
+    fn errors_test() {
+        // line numbers are the ones of the whole file, and the next puzzles are still read
+        let boards =
+            read("2\n1 2\n3 0\n\n2\n1 x\n3 0\n\n2: 1 2 3 3\n2\n1 2\n3 0\n1 2\n\n2: 0 1 2 3");
+        assert_eq!(boards.len(), 5);
+        assert!(boards[0].is_ok());
+        assert_eq!(
+            boards[1],
+            Err(ParseError::InvalidTile {
+                line: 6,
+                column: 3,
+                token: "x".to_string()
+            })
+        );
+        assert_eq!(
+            boards[2],
+            Err(ParseError::DuplicateTile {
+                line: 9,
+                column: 10,
+                value: 3
+            })
+        );
+        assert_eq!(boards[3], Err(ParseError::UnexpectedLine { line: 13 }));
+        assert!(boards[4].is_ok());
+        assert!(read("\n\n").is_empty());
+
+        // nothing is allocated from a size which overflows
+        assert_eq!(
+            read("4294967296 4294967296: 0"),
+            vec![Err(ParseError::InvalidSize {
+                line: 1,
+                token: "4294967296 4294967296".to_string()
+            })]
+        );
+    }
+
+    #[test]
+    fn open_test() {
+        let boards: Vec<_> = PuzzleReader::open("test_puzzles/puzzle04.txt")
+            .unwrap()
+            .collect();
+        assert_eq!(boards.len(), 1);
+        assert!(boards[0].is_ok());
+        assert!(PuzzleReader::open("test_puzzles/does_not_exist.txt").is_err());
+    }
+}
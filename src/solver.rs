@@ -1,34 +1,329 @@
-use grid::*;
-
-use crate::board::{Board, Directions, Heuristics};
+use crate::board::{Board, Directions, Estimate, Evaluator, Heuristics, IllegalMove, PuzzleState};
+use crate::goal::Goal;
+use crate::packed::{PackedBoard, PackedVecBoard};
+use crate::parallel::{self, Iteration};
 
 use std::cmp::Ordering;
 use std::collections::{BinaryHeap, HashMap, HashSet};
-use std::time;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::time::{Duration, Instant};
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone, Copy, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum Algo {
     ASTAR,
     IDASTAR,
+    // IDA* with the subtrees of every iteration searched by several threads
+    PARALLELIDASTAR,
+}
+
+impl FromStr for Algo {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "astar" => Ok(Algo::ASTAR),
+            "idastar" => Ok(Algo::IDASTAR),
+            "parallelidastar" => Ok(Algo::PARALLELIDASTAR),
+            _ => Err(format!("unknown algorithm '{s}'")),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+// Serialized as {"status": "solved"}, or {"status": "limit_reached", "limit": "timeout"}
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case", tag = "status", content = "limit")
+)]
+pub enum Outcome {
+    Solved,
+    Unsolvable,
+    LimitReached(Limit),
+    Cancelled,
+}
+
+// The limit of SearchLimits which stopped the search
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Limit {
+    Timeout,
+    MaxNodes,
+    MaxBound,
+    Memory,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Outcome::Solved => write!(f, "solved"),
+            Outcome::Unsolvable => write!(f, "unsolvable"),
+            Outcome::LimitReached(limit) => write!(f, "limit reached ({limit})"),
+            Outcome::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Limit::Timeout => write!(f, "timeout"),
+            Limit::MaxNodes => write!(f, "node limit"),
+            Limit::MaxBound => write!(f, "bound limit"),
+            Limit::Memory => write!(f, "memory limit"),
+        }
+    }
+}
+
+// The search stops with Outcome::LimitReached as soon as one of the limits is exceeded
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub timeout: Option<Duration>,
+    pub max_nodes: Option<usize>,
+    // highest f-value (length of the solution) the search is allowed to consider
+    pub max_bound: Option<usize>,
+    // approximate number of bytes used by the states stored during the search
+    pub max_memory: Option<usize>,
+}
+
+// Shared between threads: a solve using this token stops with Outcome::Cancelled soon after
+// cancel() is called from anywhere
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, AtomicOrdering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(AtomicOrdering::Relaxed)
+    }
+}
+
+// Statistics of one IDA* iteration (A* runs a single one)
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IterationStats {
+    pub bound: usize,
+    pub nodes_expanded: usize,
+    pub nodes_generated: usize,
+    #[cfg_attr(feature = "serde", serde(with = "seconds"))]
+    pub elapsed: Duration,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SolveResult {
+    pub outcome: Outcome,
+    pub path: Vec<Directions>,
+    pub cost: usize,
+    pub nodes_expanded: usize,
+    pub nodes_generated: usize,
+    pub iterations: Vec<IterationStats>,
+    #[cfg_attr(feature = "serde", serde(with = "seconds"))]
+    pub elapsed: Duration,
+    // best partial information when no solution was found: lowest heuristic of an expanded
+    // node and last bound tried (None when the puzzle was not searched)
+    pub lowest_h: Option<usize>,
+    pub last_bound: Option<usize>,
+}
+
+// Why a path is not a solution
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SolutionError {
+    IllegalMove(IllegalMove),
+    // the board reached at the end of the path
+    WrongFinalState(Board),
+}
+
+impl fmt::Display for SolutionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SolutionError::IllegalMove(illegal_move) => write!(f, "{illegal_move}"),
+            SolutionError::WrongFinalState(_) => write!(f, "the path does not end on the goal"),
+        }
+    }
+}
+
+impl std::error::Error for SolutionError {}
+
+// Checks that the path brings the start board to the goal
+pub fn verify_solution(
+    start: &Board,
+    goal: &Goal,
+    path: &[Directions],
+) -> Result<(), SolutionError> {
+    let board = start
+        .apply_moves(path)
+        .map_err(SolutionError::IllegalMove)?;
+    if board != *goal.get_board() {
+        return Err(SolutionError::WrongFinalState(board));
+    }
+    Ok(())
+}
+
+// Durations are serialized as a number of seconds
+#[cfg(feature = "serde")]
+mod seconds {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(duration.as_secs_f64())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let seconds = f64::deserialize(deserializer)?;
+        Duration::try_from_secs_f64(seconds).map_err(serde::de::Error::custom)
+    }
+}
+
+// Number of expanded nodes between two ticks of SearchObserver::on_expand, and two checks
+// of the clock
+pub(crate) const TICK_NODES: usize = 1024;
+
+// Events of a solve, every hook does nothing by default
+pub trait SearchObserver {
+    // A solvable puzzle is about to be searched
+    fn on_search_start(&mut self, _algo: Algo) {}
+
+    // An IDA* iteration starts with this bound
+    fn on_iteration_start(&mut self, _bound: usize) {}
+
+    // An IDA* iteration, or the whole A* search, is over
+    fn on_iteration_end(&mut self, _stats: &IterationStats) {}
+
+    // Called every TICK_NODES expanded nodes, except by the threads of the parallel IDA*
+    fn on_expand(&mut self, _nodes_expanded: usize) {}
+
+    fn on_solution(&mut self, _path: &[Directions]) {}
+
+    // Last event of every solve, including unsolvable puzzles
+    fn on_finish(&mut self, _result: &SolveResult) {}
 }
+
+// Used by the solver when no other observer is set
+pub struct NoopObserver;
+
+impl SearchObserver for NoopObserver {}
+
+// Prints the progress of the search and its result
+#[derive(Default)]
+pub struct StdoutObserver {
+    algo: Option<Algo>,
+}
+
+impl StdoutObserver {
+    pub fn new() -> Self {
+        StdoutObserver::default()
+    }
+
+    fn finish_messages(&self, result: &SolveResult) -> Vec<String> {
+        if result.outcome == Outcome::Unsolvable {
+            return vec!["This puzzle is unsolvable!".to_string()];
+        }
+        let mut messages = vec![];
+        match (self.algo, result.outcome) {
+            (Some(Algo::ASTAR), Outcome::Solved) => messages.push(format!(
+                "A* completed! Nodes explored: {}",
+                result.nodes_expanded
+            )),
+            (Some(Algo::ASTAR), Outcome::Cancelled) => messages.push(format!(
+                "A* stopped - Cancelled! Nodes explored: {}",
+                result.nodes_expanded
+            )),
+            (Some(Algo::ASTAR), _) => messages.push(format!(
+                "A* stopped - Limit reached! Nodes explored: {}",
+                result.nodes_expanded
+            )),
+            (_, Outcome::LimitReached(limit)) => {
+                messages.push(format!("IDA* stopped - {limit} reached!"))
+            }
+            (_, Outcome::Cancelled) => messages.push("IDA* stopped - Cancelled!".to_string()),
+            _ => {}
+        }
+        if result.outcome == Outcome::Solved && !result.path.is_empty() {
+            messages.push(format!("Solution found! {} steps:", result.cost));
+        }
+        if let (Some(lowest_h), Some(last_bound)) = (result.lowest_h, result.last_bound)
+            && result.outcome != Outcome::Solved
+        {
+            messages.push(format!(
+                "Best partial result - Lowest heuristic: {lowest_h}, last bound: {last_bound}"
+            ));
+        }
+        messages.push(result.solution_path_to_string());
+        messages
+    }
+}
+
+impl SearchObserver for StdoutObserver {
+    fn on_search_start(&mut self, algo: Algo) {
+        self.algo = Some(algo);
+    }
+
+    fn on_iteration_start(&mut self, bound: usize) {
+        println!("---------------------------------\nTrying depth {bound}");
+    }
+
+    fn on_iteration_end(&mut self, stats: &IterationStats) {
+        if self.algo != Some(Algo::ASTAR) {
+            println!("Time spend in depth: {:?}\n", stats.elapsed);
+        }
+    }
+
+    fn on_finish(&mut self, result: &SolveResult) {
+        for message in self.finish_messages(result) {
+            println!("{message}");
+        }
+    }
+}
+
 pub struct Solver {
-    target: Board,
+    goal: Goal,
     is_over: bool,
     solution_path: Vec<Directions>,
-    visited_cache: HashMap<(Board, usize), bool>,
+    nodes_expanded: usize,
+    nodes_generated: usize,
+    iterations: Vec<IterationStats>,
+    observer: Box<dyn SearchObserver>,
+    limits: SearchLimits,
+    time_start: Instant,
+    limit_reached: Option<Limit>,
+    cancel_token: Option<CancelToken>,
+    cancelled: bool,
+    lowest_h: Option<usize>,
+    threads: usize,
 }
 
 // Structure pour représenter un nœud dans A*
 #[derive(Clone, Eq, PartialEq)]
-struct Node {
-    board: Board,
-    path: Vec<Directions>,
+struct Node<S> {
+    board: S,
     g_score: usize, // Coût depuis le début
     f_score: usize, // g_score + heuristique
+    estimate: Estimate,
 }
 
 // Implémentation de l'ordre pour BinaryHeap (min-heap basé sur f_score)
-impl Ord for Node {
+impl<S: Eq> Ord for Node<S> {
     fn cmp(&self, other: &Self) -> Ordering {
         // Inverse pour avoir un min-heap (BinaryHeap est un max-heap par défaut)
         other
@@ -38,36 +333,23 @@ impl Ord for Node {
     }
 }
 
-impl PartialOrd for Node {
+impl<S: Eq> PartialOrd for Node<S> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl Solver {
-    pub fn new(n: usize) -> Self {
-        let mut target_vec = Vec::from_iter(1..n * n);
-        target_vec.push(0);
-        let target_grid = Grid::from_vec(target_vec, n);
-        return Solver {
-            target: Board::new(target_grid, n, (n - 1, n - 1)),
-            is_over: false,
-            solution_path: vec![],
-            visited_cache: HashMap::new(),
-        };
-    }
-
+impl SolveResult {
     pub fn solution_path_to_string(&self) -> String {
-        if !self.is_over {
+        if self.outcome != Outcome::Solved {
             return "No solution!".to_string();
         }
-        if self.solution_path.is_empty() {
+        if self.path.is_empty() {
             return "Puzzle is already won!".to_string();
         }
-        println!("Solution found! {} steps:", self.solution_path.len());
 
         let mut solution = "".to_string();
-        for step in self.solution_path.clone() {
+        for step in &self.path {
             match step {
                 Directions::DOWN => solution += "DOWN ",
                 Directions::UP => solution += "UP ",
@@ -75,56 +357,221 @@ impl Solver {
                 Directions::RIGHT => solution += "RIGHT ",
             }
         }
-        return solution.to_string();
+        solution
     }
+}
 
-    pub fn solve(&mut self, init_board: Board, algo: Algo, heuristic: Heuristics) {
-        match algo {
-            Algo::ASTAR => self.a_star(init_board, &heuristic),
-            Algo::IDASTAR => self.id_a_star(init_board, &heuristic),
+impl Solver {
+    pub fn new(rows: usize, cols: usize) -> Self {
+        Solver::with_goal(Goal::standard(rows, cols))
+    }
+
+    pub fn with_goal(goal: Goal) -> Self {
+        Solver {
+            goal,
+            is_over: false,
+            solution_path: vec![],
+            nodes_expanded: 0,
+            nodes_generated: 0,
+            iterations: vec![],
+            observer: Box::new(NoopObserver),
+            limits: SearchLimits::default(),
+            time_start: Instant::now(),
+            limit_reached: None,
+            cancel_token: None,
+            cancelled: false,
+            lowest_h: None,
+            threads: std::thread::available_parallelism().map_or(1, |threads| threads.get()),
         }
-        println!("{}", self.solution_path_to_string());
     }
 
-    fn is_target(&self, board: &Board) -> bool {
-        return *board == self.target;
+    pub fn set_observer(&mut self, observer: Box<dyn SearchObserver>) {
+        self.observer = observer;
     }
 
-    fn a_star(&mut self, init_board: Board, heuristic: &Heuristics) {
+    pub fn set_limits(&mut self, limits: SearchLimits) {
+        self.limits = limits;
+    }
+
+    // The token is checked on every expanded node of the next solves
+    pub fn set_cancel_token(&mut self, token: CancelToken) {
+        self.cancel_token = Some(token);
+    }
+
+    // Threads used by Algo::PARALLELIDASTAR, every core by default
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    // A limit was reached or the search was cancelled
+    fn is_stopped(&self) -> bool {
+        self.limit_reached.is_some() || self.cancelled
+    }
+
+    // Counts one more expanded node, with its heuristic and the memory used by the search,
+    // and checks the limits of the search
+    fn expand(&mut self, h: usize, memory: usize) -> bool {
+        self.nodes_expanded += 1;
+        self.lowest_h = Some(self.lowest_h.map_or(h, |lowest| lowest.min(h)));
+        if let Some(max_nodes) = self.limits.max_nodes
+            && self.nodes_expanded > max_nodes
+        {
+            self.limit_reached = Some(Limit::MaxNodes);
+        }
+        if let Some(max_memory) = self.limits.max_memory
+            && memory > max_memory
+        {
+            self.limit_reached = Some(Limit::Memory);
+        }
+        // checking the clock on every node is not worth it
+        if self.nodes_expanded.is_multiple_of(TICK_NODES) {
+            self.observer.on_expand(self.nodes_expanded);
+            if let Some(timeout) = self.limits.timeout
+                && self.time_start.elapsed() > timeout
+            {
+                self.limit_reached = Some(Limit::Timeout);
+            }
+        }
+        if let Some(token) = &self.cancel_token
+            && token.is_cancelled()
+        {
+            self.cancelled = true;
+        }
+        !self.is_stopped()
+    }
+
+    pub fn solve(&mut self, init_board: Board, algo: Algo, heuristic: Heuristics) -> SolveResult {
+        let time_start = Instant::now();
+        self.time_start = time_start;
+        self.limit_reached = None;
+        self.cancelled = false;
+        self.lowest_h = None;
+        self.is_over = false;
+        self.solution_path.clear();
+        self.nodes_expanded = 0;
+        self.nodes_generated = 0;
+        self.iterations.clear();
+
+        // no need to search half of the state space to find out there is no solution
+        let outcome = if !init_board.is_solvable(&self.goal) {
+            Outcome::Unsolvable
+        } else {
+            self.observer.on_search_start(algo);
+            let evaluator = Evaluator::new(heuristic, &self.goal);
+            // the smallest representation available for this size is used during the search
+            if let Some(init) = PackedBoard::from_state(&init_board) {
+                let target = PackedBoard::from_state(self.goal.get_board()).unwrap();
+                self.search(init, target, algo, &evaluator);
+            } else if let Some(init) = PackedVecBoard::from_state(&init_board) {
+                let target = PackedVecBoard::from_state(self.goal.get_board()).unwrap();
+                self.search(init, target, algo, &evaluator);
+            } else {
+                let target = self.goal.get_board().clone();
+                self.search(init_board, target, algo, &evaluator);
+            }
+            if self.is_over {
+                Outcome::Solved
+            } else if self.cancelled {
+                Outcome::Cancelled
+            } else if let Some(limit) = self.limit_reached {
+                Outcome::LimitReached(limit)
+            } else {
+                // the whole reachable space was explored
+                Outcome::Unsolvable
+            }
+        };
+
+        let result = SolveResult {
+            outcome,
+            path: self.solution_path.clone(),
+            cost: self.solution_path.len(),
+            nodes_expanded: self.nodes_expanded,
+            nodes_generated: self.nodes_generated,
+            iterations: self.iterations.clone(),
+            elapsed: time_start.elapsed(),
+            lowest_h: self.lowest_h,
+            last_bound: self.iterations.last().map(|iteration| iteration.bound),
+        };
+        if outcome == Outcome::Solved {
+            self.observer.on_solution(&result.path);
+        }
+        self.observer.on_finish(&result);
+        result
+    }
+
+    pub fn get_goal(&self) -> &Goal {
+        &self.goal
+    }
+
+    pub fn is_target(&self, board: &Board) -> bool {
+        board == self.goal.get_board()
+    }
+
+    fn search<S: PuzzleState + Send + Sync>(
+        &mut self,
+        init: S,
+        target: S,
+        algo: Algo,
+        evaluator: &Evaluator,
+    ) {
+        match algo {
+            Algo::ASTAR => self.a_star(init, &target, evaluator),
+            Algo::IDASTAR => self.id_a_star(init, &target, evaluator),
+            Algo::PARALLELIDASTAR => self.parallel_id_a_star(init, &target, evaluator),
+        }
+    }
+
+    fn a_star<S: PuzzleState>(&mut self, init_board: S, target: &S, evaluator: &Evaluator) {
+        let time_start = Instant::now();
+
         // priority queue for the nodes to explore
         let mut open_heap = BinaryHeap::new();
 
         // avoid visiting nodes in the path
         let mut open_set = HashSet::new();
 
-        // best scores per state
-        let mut g_scores = HashMap::new();
+        // best scores per state, with the move leading to it from its best parent
+        let mut g_scores: HashMap<S, (usize, Option<Directions>)> = HashMap::new();
 
         // fully explored states
         let mut closed_set = HashSet::new();
 
-        let h_score = init_board.heuristic(*heuristic);
+        let estimate = evaluator.estimate(&init_board);
+        let state_size = size_of::<S>() + init_board.heap_size();
         let start_node = Node {
             board: init_board.clone(),
-            path: vec![],
             g_score: 0,
-            f_score: h_score,
+            f_score: estimate.value,
+            estimate,
         };
 
         open_heap.push(start_node);
         open_set.insert(init_board.clone());
-        g_scores.insert(init_board.clone(), 0);
-
-        let mut nodes_explored = 0;
+        g_scores.insert(init_board.clone(), (0, None));
 
         while let Some(current) = open_heap.pop() {
-            nodes_explored += 1;
+            // every remaining node is at least as long as this one
+            if let Some(max_bound) = self.limits.max_bound
+                && current.f_score > max_bound
+            {
+                self.limit_reached = Some(Limit::MaxBound);
+            }
+            // every stored state is counted with its entry in the maps, and the heap nodes
+            let memory = (g_scores.len() + closed_set.len() + open_set.len() + open_heap.len())
+                * state_size
+                + g_scores.len() * size_of::<(usize, Option<Directions>)>()
+                + open_heap.len() * size_of::<(usize, usize)>();
+            let current_h = current.estimate.value;
+            if self.is_stopped() || !self.expand(current_h, memory) {
+                self.end_a_star(current.f_score, time_start);
+                return;
+            }
             open_set.remove(&current.board);
 
-            if self.is_target(&current.board) {
+            if current.board == *target {
                 self.is_over = true;
-                self.solution_path = current.path;
-                println!("A* completed! Nodes explored: {}", nodes_explored);
+                self.solution_path = rebuild_path(&g_scores, current.board);
+                self.end_a_star(current.g_score, time_start);
                 return;
             }
 
@@ -132,7 +579,8 @@ impl Solver {
 
             for dir in current.board.next_directions() {
                 let mut neighbor_board = current.board.clone();
-                neighbor_board.make_move(dir);
+                let estimate = evaluator.make_move(&mut neighbor_board, dir, current.estimate);
+                self.nodes_generated += 1;
 
                 if closed_set.contains(&neighbor_board) {
                     continue;
@@ -142,25 +590,21 @@ impl Solver {
 
                 // Check if this path is better
                 let is_better = match g_scores.get(&neighbor_board) {
-                    Some(&existing_g) => tentative_g_score < existing_g,
+                    Some(&(existing_g, _)) => tentative_g_score < existing_g,
                     None => true,
                 };
 
                 if is_better {
                     // update best path
-                    g_scores.insert(neighbor_board.clone(), tentative_g_score);
-
-                    let h_score = neighbor_board.heuristic(*heuristic);
-                    let f_score = tentative_g_score + h_score;
+                    g_scores.insert(neighbor_board.clone(), (tentative_g_score, Some(dir)));
 
-                    let mut new_path = current.path.clone();
-                    new_path.push(dir);
+                    let f_score = tentative_g_score + estimate.value;
 
                     let neighbor_node = Node {
                         board: neighbor_board.clone(),
-                        path: new_path,
                         g_score: tentative_g_score,
                         f_score,
+                        estimate,
                     };
 
                     // only add the set if it's not in it
@@ -172,93 +616,211 @@ impl Solver {
             }
         }
 
-        println!(
-            "A* completed - No solution found! Nodes explored: {}",
-            nodes_explored
-        );
+        self.end_a_star(0, time_start);
     }
 
-    fn id_a_star(&mut self, init_board: Board, heuristic: &Heuristics) {
-        for i in init_board.heuristic(*heuristic)..100 {
-            let time_start = time::SystemTime::now();
-            println!("---------------------------------\nTrying depth {i}");
+    // A* is reported as a single iteration bounded by the cost of the solution
+    fn end_a_star(&mut self, bound: usize, time_start: Instant) {
+        let stats = IterationStats {
+            bound,
+            nodes_expanded: self.nodes_expanded,
+            nodes_generated: self.nodes_generated,
+            elapsed: time_start.elapsed(),
+        };
+        self.observer.on_iteration_end(&stats);
+        self.iterations.push(stats);
+    }
 
-            self.visited_cache.clear();
-            let mut path_states = HashSet::new();
-            path_states.insert(init_board.clone());
+    fn id_a_star<S: PuzzleState>(&mut self, init_board: S, target: &S, evaluator: &Evaluator) {
+        let init = evaluator.estimate(&init_board);
+        // every move changes the parity of the blank's position, so the length of any solution has
+        // the parity of the distance between the blank and its goal: other bounds can be skipped
+        let parity = blank_distance(&init_board, target) % 2;
+        // the same board and path are modified in place during the whole search
+        let mut board = init_board;
+        let mut path = Vec::new();
+        let mut bound = next_bound(init.value, parity);
+        loop {
+            if let Some(max_bound) = self.limits.max_bound
+                && bound > max_bound
+            {
+                self.limit_reached = Some(Limit::MaxBound);
+                break;
+            }
+            let time_start = Instant::now();
+            let expanded_before = self.nodes_expanded;
+            let generated_before = self.nodes_generated;
+            self.observer.on_iteration_start(bound);
 
-            self.dfs(init_board.clone(), vec![], path_states, 0, i, heuristic);
-            println!("Time spend in depth: {:?}\n", time_start.elapsed().unwrap());
-            if self.is_over {
+            let min_exceeded = self.dfs(&mut board, init, target, &mut path, bound, evaluator);
+            let stats = IterationStats {
+                bound,
+                nodes_expanded: self.nodes_expanded - expanded_before,
+                nodes_generated: self.nodes_generated - generated_before,
+                elapsed: time_start.elapsed(),
+            };
+            self.observer.on_iteration_end(&stats);
+            self.iterations.push(stats);
+            // no node was cut by the bound: the whole reachable space was explored
+            if self.is_over || self.is_stopped() || min_exceeded == usize::MAX {
                 break;
             }
+            bound = next_bound(min_exceeded, parity);
         }
     }
 
-    fn dfs(
+    // Same iterations as id_a_star, each of them being run by several threads
+    fn parallel_id_a_star<S: PuzzleState + Send + Sync>(
         &mut self,
-        current_board: Board,
-        path: Vec<Directions>,
-        path_states: HashSet<Board>,
-        depth: usize,
-        max_depth: usize,
-        heuristic: &Heuristics,
+        init_board: S,
+        target: &S,
+        evaluator: &Evaluator,
     ) {
-        if self.is_over || depth + current_board.heuristic(*heuristic) > max_depth {
-            return;
-        }
+        let init = evaluator.estimate(&init_board);
+        let parity = blank_distance(&init_board, target) % 2;
+        let mut bound = next_bound(init.value, parity);
+        loop {
+            if let Some(max_bound) = self.limits.max_bound
+                && bound > max_bound
+            {
+                self.limit_reached = Some(Limit::MaxBound);
+                break;
+            }
+            let time_start = Instant::now();
+            self.observer.on_iteration_start(bound);
 
-        // Check if this state has already been explored at this depth or less
-        if let Some(&explored) = self.visited_cache.get(&(current_board.clone(), depth)) {
-            if explored {
-                return;
+            let iteration = Iteration {
+                evaluator,
+                bound,
+                limits: self.limits,
+                time_start: self.time_start,
+                cancel_token: self.cancel_token.as_ref(),
+                nodes_before: self.nodes_expanded,
+                threads: self.threads,
+            };
+            let result = parallel::run_iteration(&iteration, &init_board, init, target);
+            self.nodes_expanded += result.nodes_expanded;
+            self.nodes_generated += result.nodes_generated;
+            if let Some(h) = result.lowest_h {
+                self.lowest_h = Some(self.lowest_h.map_or(h, |lowest| lowest.min(h)));
+            }
+            if result.limit_reached.is_some() {
+                self.limit_reached = result.limit_reached;
             }
+            self.cancelled |= result.cancelled;
+            if let Some(solution) = result.solution {
+                self.is_over = true;
+                self.solution_path = solution;
+            }
+
+            let stats = IterationStats {
+                bound,
+                nodes_expanded: result.nodes_expanded,
+                nodes_generated: result.nodes_generated,
+                elapsed: time_start.elapsed(),
+            };
+            self.observer.on_iteration_end(&stats);
+            self.iterations.push(stats);
+            if self.is_over || self.is_stopped() || result.min_exceeded == usize::MAX {
+                break;
+            }
+            bound = next_bound(result.min_exceeded, parity);
+        }
+    }
+
+    // Returns the smallest f-value above the bound among the cut nodes
+    fn dfs<S: PuzzleState>(
+        &mut self,
+        current_board: &mut S,
+        estimate: Estimate,
+        target: &S,
+        path: &mut Vec<Directions>,
+        max_depth: usize,
+        evaluator: &Evaluator,
+    ) -> usize {
+        let current_h = estimate.value;
+        let f_score = path.len() + current_h;
+        if f_score > max_depth {
+            return f_score;
+        }
+        if self.is_over || self.is_stopped() {
+            return usize::MAX;
         }
 
-        if self.is_target(&current_board) {
+        if *current_board == *target {
             // println!("Target attained! Length of path: {}", path.len());
             self.is_over = true;
             self.solution_path = path.clone();
-            return;
+            return f_score;
         }
 
-        self.visited_cache
-            .insert((current_board.clone(), depth), false);
-
-        for dir in current_board.next_directions() {
-            // println!("dir {dir}");
-            let mut new_board = current_board.clone();
-            new_board.make_move(dir);
+        // only the board and the path are stored
+        let memory =
+            size_of::<S>() + current_board.heap_size() + path.capacity() * size_of::<Directions>();
+        if !self.expand(current_h, memory) {
+            return usize::MAX;
+        }
 
-            // avoid cycles
-            if path_states.contains(&new_board) {
+        let mut min_exceeded = usize::MAX;
+        for dir in Directions::ALL {
+            // undoing the previous move is never useful, longer cycles can't be shorter than
+            // a solution found in an earlier iteration
+            if !current_board.can_move(dir) || path.last() == Some(&dir.opposite()) {
                 continue;
             }
+            let child = evaluator.make_move(current_board, dir, estimate);
+            self.nodes_generated += 1;
+            path.push(dir);
 
-            let mut temp_path = path.clone();
-            temp_path.push(dir.clone());
-
-            let mut new_path_states = path_states.clone();
-            new_path_states.insert(new_board.clone());
+            let child_min = self.dfs(current_board, child, target, path, max_depth, evaluator);
+            min_exceeded = min_exceeded.min(child_min);
 
-            self.dfs(
-                new_board,
-                temp_path,
-                new_path_states,
-                depth + 1,
-                max_depth,
-                &heuristic,
-            );
+            path.pop();
+            current_board.make_move(dir.opposite());
+            if self.is_over {
+                break;
+            }
         }
-        self.visited_cache
-            .insert((current_board.clone(), depth), true);
+        min_exceeded
     }
 }
 
+// Manhattan distance between the blank of two boards
+fn blank_distance<S: PuzzleState>(board: &S, target: &S) -> usize {
+    let (row, col) = board.get_pos_0();
+    let (target_row, target_col) = target.get_pos_0();
+    row.abs_diff(target_row) + col.abs_diff(target_col)
+}
+
+// Smallest bound at least `f_score` with the parity of the solutions
+fn next_bound(f_score: usize, parity: usize) -> usize {
+    if f_score % 2 == parity {
+        f_score
+    } else {
+        f_score + 1
+    }
+}
+
+// Walks back from the target to the initial state using the best move leading to every state
+fn rebuild_path<S: PuzzleState>(
+    g_scores: &HashMap<S, (usize, Option<Directions>)>,
+    target: S,
+) -> Vec<Directions> {
+    let mut path = vec![];
+    let mut state = target;
+    while let Some(&(_, Some(dir))) = g_scores.get(&state) {
+        path.push(dir);
+        state.make_move(dir.opposite());
+    }
+    path.reverse();
+    path
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::board::Directions::*;
+    use grid::*;
 
     #[test]
     fn new_test() {
@@ -269,14 +831,10 @@ mod tests {
         7 8 6
         */
         let n = 3;
-        let solver = Solver::new(n);
-        assert_eq!(solver.target.get_n(), 3);
+        let solver = Solver::new(n, n);
+        assert_eq!(solver.get_goal().get_size(), (3, 3));
         assert_eq!(
-            *solver.target.get_grid(),
-            grid![[1, 2, 3][4, 5, 6][7, 8, 0]]
-        );
-        assert_eq!(
-            *solver.target.get_grid(),
+            *solver.get_goal().get_board().get_grid(),
             grid![[1, 2, 3][4, 5, 6][7, 8, 0]]
         );
     }
@@ -284,10 +842,18 @@ mod tests {
     #[test]
     fn solution_path_to_string_test() {
         let solution_vector = vec![DOWN, LEFT, LEFT, RIGHT, UP];
-        let mut solver = Solver::new(1);
-        solver.is_over = true;
-        solver.solution_path = solution_vector;
-        assert_eq!(solver.solution_path_to_string(), "DOWN LEFT LEFT RIGHT UP ");
+        let result = SolveResult {
+            outcome: Outcome::Solved,
+            cost: solution_vector.len(),
+            path: solution_vector,
+            nodes_expanded: 0,
+            nodes_generated: 0,
+            iterations: vec![],
+            elapsed: Duration::ZERO,
+            lowest_h: None,
+            last_bound: None,
+        };
+        assert_eq!(result.solution_path_to_string(), "DOWN LEFT LEFT RIGHT UP ");
     }
 
     #[test]
@@ -300,8 +866,8 @@ mod tests {
         */
         let n = 3;
         let input_str = "1 2 3\n4 5 6\n7 8 0";
-        let board = Board::load_from_str(n, input_str);
-        let solver = Solver::new(n);
+        let board = Board::load_from_str(n, n, input_str).unwrap();
+        let solver = Solver::new(n, n);
         assert!(solver.is_target(&board))
     }
 
@@ -315,8 +881,8 @@ mod tests {
         */
         let n = 3;
         let input_str = "0 1 3\n4 2 5\n7 8 6";
-        let board = Board::load_from_str(n, input_str);
-        let solver = Solver::new(n);
+        let board = Board::load_from_str(n, n, input_str).unwrap();
+        let solver = Solver::new(n, n);
         assert!(!solver.is_target(&board))
     }
 
@@ -330,10 +896,449 @@ mod tests {
         */
         let n = 3;
         let input_str = "0 1 3\n4 2 5\n7 8 6";
-        let board = Board::load_from_str(n, input_str);
-        let mut solver = Solver::new(3);
-        solver.solve(board, Algo::IDASTAR, Heuristics::NONE);
-        assert!(solver.is_over);
-        assert_eq!(solver.solution_path.len(), 4)
+        let board = Board::load_from_str(n, n, input_str).unwrap();
+        let mut solver = Solver::new(3, 3);
+        let result = solver.solve(board, Algo::IDASTAR, Heuristics::NONE);
+        assert_eq!(result.outcome, Outcome::Solved);
+        assert_eq!(result.path, vec![RIGHT, DOWN, RIGHT, DOWN]);
+        assert_eq!(result.cost, 4);
+        // no heuristic: the solution has an even length, so only 0, 2 and 4 are tried
+        assert_eq!(result.iterations.len(), 3);
+        assert_eq!(result.iterations[2].bound, 4);
+        assert!(result.nodes_expanded > 0);
+    }
+
+    #[test]
+    fn unsolvable_test() {
+        /*
+        3
+        1 2 3
+        4 5 6
+        8 7 0
+        */
+        let n = 3;
+        let input_str = "1 2 3\n4 5 6\n8 7 0";
+        let board = Board::load_from_str(n, n, input_str).unwrap();
+        let mut solver = Solver::new(n, n);
+        let result = solver.solve(board, Algo::ASTAR, Heuristics::LINCONFLICT);
+        assert_eq!(result.outcome, Outcome::Unsolvable);
+        assert!(result.path.is_empty());
+        assert_eq!(result.nodes_expanded, 0);
+    }
+
+    #[test]
+    fn a_star_test() {
+        /*
+        3
+        0 1 3
+        4 2 5
+        7 8 6
+        */
+        let n = 3;
+        let input_str = "0 1 3\n4 2 5\n7 8 6";
+        let board = Board::load_from_str(n, n, input_str).unwrap();
+        let mut solver = Solver::new(n, n);
+        let result = solver.solve(board, Algo::ASTAR, Heuristics::MANHATTAN);
+        assert_eq!(result.outcome, Outcome::Solved);
+        assert_eq!(result.cost, 4);
+        assert!(result.nodes_generated >= result.nodes_expanded);
+    }
+
+    #[test]
+    fn goal_test() {
+        /*
+        3
+        1 2 3
+        7 8 4
+        0 6 5
+        */
+        let board = Board::load_from_str(3, 3, "1 2 3\n7 8 4\n0 6 5").unwrap();
+        let mut solver = Solver::with_goal(Goal::snail(3, 3));
+        for algo in [Algo::ASTAR, Algo::IDASTAR] {
+            for heuristic in [Heuristics::LINCONFLICT, Heuristics::PATTERNDB] {
+                let result = solver.solve(board.clone(), algo, heuristic);
+                assert_eq!(result.outcome, Outcome::Solved);
+                assert_eq!(result.path, vec![UP, RIGHT]);
+            }
+        }
+        let result = solver.solve(Board::solved(3, 3), Algo::ASTAR, Heuristics::LINCONFLICT);
+        assert_eq!(result.outcome, Outcome::Unsolvable);
+    }
+
+    #[test]
+    fn rectangular_test() {
+        /*
+        3 4
+         9  5  7  0
+         2 10  1  6
+        11  4  8  3
+        */
+        let board = Board::load_from_str(3, 4, "9 5 7 0\n2 10 1 6\n11 4 8 3").unwrap();
+        let mut solver = Solver::new(3, 4);
+        for algo in [Algo::ASTAR, Algo::IDASTAR] {
+            let result = solver.solve(board.clone(), algo, Heuristics::LINCONFLICT);
+            assert_eq!(result.outcome, Outcome::Solved);
+            assert_eq!(result.cost, 32);
+        }
+
+        /*
+        2 4
+        5 1 3 4
+        2 0 6 7
+        */
+        let board = Board::load_from_str(2, 4, "5 1 3 4\n2 0 6 7").unwrap();
+        let mut solver = Solver::new(2, 4);
+        let result = solver.solve(board, Algo::IDASTAR, Heuristics::PATTERNDB);
+        assert_eq!(result.outcome, Outcome::Solved);
+        assert_eq!(result.cost, 6);
+    }
+
+    #[test]
+    fn cancel_test() {
+        let board = Board::load_from_str(4, 4, "0 12 9 13\n15 11 10 14\n3 7 2 5\n4 8 6 1").unwrap();
+        let mut solver = Solver::new(4, 4);
+        for algo in [Algo::ASTAR, Algo::IDASTAR] {
+            let token = CancelToken::new();
+            solver.set_cancel_token(token.clone());
+            let handle = std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(50));
+                token.cancel();
+            });
+            let result = solver.solve(board.clone(), algo, Heuristics::MANHATTAN);
+            handle.join().unwrap();
+            assert_eq!(result.outcome, Outcome::Cancelled);
+            assert!(result.elapsed < Duration::from_secs(5));
+        }
+
+        // an already cancelled token stops on the first node
+        let result = solver.solve(board, Algo::IDASTAR, Heuristics::MANHATTAN);
+        assert_eq!(result.outcome, Outcome::Cancelled);
+        assert_eq!(result.nodes_expanded, 1);
+    }
+
+    #[test]
+    fn parallel_id_a_star_test() {
+        // same boards as id_a_star_bounds_test and rectangular_test
+        for (rows, cols, input_str, cost) in [
+            (3, 3, "8 6 7\n2 5 4\n3 0 1", 31),
+            (3, 4, "9 5 7 0\n2 10 1 6\n11 4 8 3", 32),
+            (3, 3, "1 2 3\n4 5 6\n7 0 8", 1),
+            (3, 3, "1 2 3\n4 5 6\n7 8 0", 0),
+        ] {
+            let board = Board::load_from_str(rows, cols, input_str).unwrap();
+            let mut solver = Solver::new(rows, cols);
+            let sequential = solver.solve(board.clone(), Algo::IDASTAR, Heuristics::LINCONFLICT);
+            for threads in [1, 4] {
+                solver.set_threads(threads);
+                let result = solver.solve(
+                    board.clone(),
+                    Algo::PARALLELIDASTAR,
+                    Heuristics::LINCONFLICT,
+                );
+                assert_eq!(result.outcome, Outcome::Solved);
+                assert_eq!(result.cost, cost);
+                assert_eq!(result.cost, sequential.cost);
+                let bounds = |result: &SolveResult| -> Vec<usize> {
+                    result.iterations.iter().map(|it| it.bound).collect()
+                };
+                assert_eq!(bounds(&result), bounds(&sequential));
+
+                // the path must lead to the goal
+                let mut replay = board.clone();
+                for &dir in &result.path {
+                    assert!(replay.can_move(dir));
+                    replay.make_move(dir);
+                }
+                assert!(solver.is_target(&replay));
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_limits_test() {
+        let board = Board::load_from_str(4, 4, "0 12 9 13\n15 11 10 14\n3 7 2 5\n4 8 6 1").unwrap();
+        let mut solver = Solver::new(4, 4);
+        solver.set_threads(4);
+        solver.set_limits(SearchLimits {
+            max_nodes: Some(100_000),
+            ..Default::default()
+        });
+        let result = solver.solve(board.clone(), Algo::PARALLELIDASTAR, Heuristics::MANHATTAN);
+        assert_eq!(result.outcome, Outcome::LimitReached(Limit::MaxNodes));
+        // every thread may go up to TICK_NODES nodes over the limit
+        assert!(result.nodes_expanded <= 100_000 + 4 * TICK_NODES);
+
+        solver.set_limits(SearchLimits::default());
+        let token = CancelToken::new();
+        token.cancel();
+        solver.set_cancel_token(token);
+        let result = solver.solve(board.clone(), Algo::PARALLELIDASTAR, Heuristics::MANHATTAN);
+        assert_eq!(result.outcome, Outcome::Cancelled);
+        assert_eq!(result.nodes_expanded, 1);
+
+        // limits reached while splitting the tree between the threads
+        let mut solver = Solver::new(4, 4);
+        solver.set_threads(4);
+        solver.set_limits(SearchLimits {
+            max_nodes: Some(10),
+            ..Default::default()
+        });
+        let result = solver.solve(board.clone(), Algo::PARALLELIDASTAR, Heuristics::MANHATTAN);
+        assert_eq!(result.outcome, Outcome::LimitReached(Limit::MaxNodes));
+        assert_eq!(result.nodes_expanded, 11);
+
+        // the subtrees waiting for a thread count in the memory
+        solver.set_threads(usize::MAX);
+        solver.set_limits(SearchLimits {
+            max_memory: Some(100_000),
+            ..Default::default()
+        });
+        let result = solver.solve(board, Algo::PARALLELIDASTAR, Heuristics::MANHATTAN);
+        assert_eq!(result.outcome, Outcome::LimitReached(Limit::Memory));
+    }
+
+    struct RecordingObserver(std::rc::Rc<std::cell::RefCell<Vec<String>>>);
+
+    impl SearchObserver for RecordingObserver {
+        fn on_search_start(&mut self, algo: Algo) {
+            self.0.borrow_mut().push(format!("start {algo:?}"));
+        }
+
+        fn on_iteration_start(&mut self, bound: usize) {
+            self.0.borrow_mut().push(format!("iteration {bound}"));
+        }
+
+        fn on_iteration_end(&mut self, stats: &IterationStats) {
+            self.0.borrow_mut().push(format!("end {}", stats.bound));
+        }
+
+        fn on_expand(&mut self, nodes_expanded: usize) {
+            self.0.borrow_mut().push(format!("tick {nodes_expanded}"));
+        }
+
+        fn on_solution(&mut self, path: &[Directions]) {
+            self.0.borrow_mut().push(format!("solution {}", path.len()));
+        }
+
+        fn on_finish(&mut self, result: &SolveResult) {
+            self.0
+                .borrow_mut()
+                .push(format!("finish {}", result.outcome));
+        }
+    }
+
+    #[test]
+    fn observer_test() {
+        let n = 3;
+        let input_str = "0 1 3\n4 2 5\n7 8 6";
+        let board = Board::load_from_str(n, n, input_str).unwrap();
+        let messages = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let mut solver = Solver::new(n, n);
+        solver.set_observer(Box::new(RecordingObserver(messages.clone())));
+        solver.solve(board.clone(), Algo::IDASTAR, Heuristics::NONE);
+        assert_eq!(
+            *messages.borrow(),
+            vec![
+                "start IDASTAR",
+                "iteration 0",
+                "end 0",
+                "iteration 2",
+                "end 2",
+                "iteration 4",
+                "end 4",
+                "solution 4",
+                "finish solved"
+            ]
+        );
+
+        messages.borrow_mut().clear();
+        solver.solve(board, Algo::ASTAR, Heuristics::MANHATTAN);
+        assert_eq!(
+            *messages.borrow(),
+            vec!["start ASTAR", "end 4", "solution 4", "finish solved"]
+        );
+
+        messages.borrow_mut().clear();
+        let board = Board::load_from_str(n, n, "1 2 3\n4 5 6\n8 7 0").unwrap();
+        solver.solve(board, Algo::ASTAR, Heuristics::MANHATTAN);
+        assert_eq!(*messages.borrow(), vec!["finish unsolvable"]);
+    }
+
+    #[test]
+    fn observer_ticks_test() {
+        let board = Board::load_from_str(3, 3, "8 6 7\n2 5 4\n3 0 1").unwrap();
+        let messages = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let mut solver = Solver::new(3, 3);
+        solver.set_observer(Box::new(RecordingObserver(messages.clone())));
+        let result = solver.solve(board, Algo::IDASTAR, Heuristics::MANHATTAN);
+        let ticks: Vec<String> = messages
+            .borrow()
+            .iter()
+            .filter(|m| m.starts_with("tick"))
+            .cloned()
+            .collect();
+        assert_eq!(ticks.len(), result.nodes_expanded / TICK_NODES);
+        assert_eq!(ticks[0], format!("tick {TICK_NODES}"));
+    }
+
+    #[test]
+    fn stdout_observer_test() {
+        let n = 3;
+        let board = Board::load_from_str(n, n, "0 1 3\n4 2 5\n7 8 6").unwrap();
+        let mut solver = Solver::new(n, n);
+        let result = solver.solve(board.clone(), Algo::ASTAR, Heuristics::MANHATTAN);
+        let mut observer = StdoutObserver::new();
+        observer.on_search_start(Algo::ASTAR);
+        assert_eq!(
+            observer.finish_messages(&result),
+            vec![
+                "A* completed! Nodes explored: 5",
+                "Solution found! 4 steps:",
+                "RIGHT DOWN RIGHT DOWN "
+            ]
+        );
+
+        solver.set_limits(SearchLimits {
+            max_nodes: Some(2),
+            ..Default::default()
+        });
+        let result = solver.solve(board, Algo::IDASTAR, Heuristics::MANHATTAN);
+        observer.on_search_start(Algo::IDASTAR);
+        assert_eq!(
+            observer.finish_messages(&result),
+            vec![
+                "IDA* stopped - node limit reached!",
+                "Best partial result - Lowest heuristic: 2, last bound: 4",
+                "No solution!"
+            ]
+        );
+    }
+
+    #[test]
+    fn limits_test() {
+        /*
+        3
+        8 6 7
+        2 5 4
+        3 0 1
+        */
+        let n = 3;
+        let input_str = "8 6 7\n2 5 4\n3 0 1";
+        for algo in [Algo::ASTAR, Algo::IDASTAR] {
+            let board = Board::load_from_str(n, n, input_str).unwrap();
+            let mut solver = Solver::new(n, n);
+            solver.set_limits(SearchLimits {
+                max_nodes: Some(100),
+                ..Default::default()
+            });
+            let result = solver.solve(board, algo, Heuristics::MANHATTAN);
+            assert_eq!(result.outcome, Outcome::LimitReached(Limit::MaxNodes));
+            assert!(result.path.is_empty());
+            assert_eq!(result.nodes_expanded, 101);
+            // the partial result is at least as close as the initial board
+            assert!(result.lowest_h.unwrap() <= 21);
+            assert!(result.last_bound.unwrap() >= 21);
+        }
+    }
+
+    #[test]
+    fn memory_limit_test() {
+        /*
+        3
+        8 6 7
+        2 5 4
+        3 0 1
+        */
+        let board = Board::load_from_str(3, 3, "8 6 7\n2 5 4\n3 0 1").unwrap();
+        let mut solver = Solver::new(3, 3);
+        solver.set_limits(SearchLimits {
+            max_memory: Some(100_000),
+            ..Default::default()
+        });
+        let result = solver.solve(board.clone(), Algo::ASTAR, Heuristics::MANHATTAN);
+        assert_eq!(result.outcome, Outcome::LimitReached(Limit::Memory));
+        assert!(result.nodes_expanded < 10_000);
+
+        // IDA* only stores the current path
+        let result = solver.solve(board, Algo::IDASTAR, Heuristics::MANHATTAN);
+        assert_eq!(result.outcome, Outcome::Solved);
+    }
+
+    #[test]
+    fn timeout_test() {
+        let board = Board::load_from_str(4, 4, "0 12 9 13\n15 11 10 14\n3 7 2 5\n4 8 6 1").unwrap();
+        let mut solver = Solver::new(4, 4);
+        solver.set_limits(SearchLimits {
+            timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        });
+        for algo in [Algo::ASTAR, Algo::IDASTAR] {
+            let result = solver.solve(board.clone(), algo, Heuristics::MANHATTAN);
+            assert_eq!(result.outcome, Outcome::LimitReached(Limit::Timeout));
+            assert!(result.elapsed < Duration::from_secs(5));
+        }
+    }
+
+    #[test]
+    fn id_a_star_bounds_test() {
+        /*
+        3
+        8 6 7
+        2 5 4
+        3 0 1
+        */
+        let n = 3;
+        let input_str = "8 6 7\n2 5 4\n3 0 1";
+        let board = Board::load_from_str(n, n, input_str).unwrap();
+        let mut solver = Solver::new(n, n);
+        let result = solver.solve(board.clone(), Algo::IDASTAR, Heuristics::LINCONFLICT);
+        assert_eq!(result.outcome, Outcome::Solved);
+        assert_eq!(result.cost, 31);
+        // every bound is the smallest f-value cut by the previous iteration, with an odd parity
+        let bounds: Vec<usize> = result.iterations.iter().map(|it| it.bound).collect();
+        assert!(bounds.windows(2).all(|w| w[0] < w[1]));
+        assert!(bounds.iter().all(|b| b % 2 == 1));
+        assert_eq!(*bounds.last().unwrap(), 31);
+
+        for algo in [Algo::ASTAR, Algo::IDASTAR] {
+            solver.set_limits(SearchLimits {
+                max_bound: Some(29),
+                ..Default::default()
+            });
+            let result = solver.solve(board.clone(), algo, Heuristics::LINCONFLICT);
+            assert_eq!(result.outcome, Outcome::LimitReached(Limit::MaxBound));
+            assert!(result.last_bound.unwrap() <= 31);
+        }
+    }
+
+    #[test]
+    fn verify_solution_test() {
+        let start: Board = "3\n0 1 3\n4 2 5\n7 8 6".parse().unwrap();
+        let goal = Goal::standard(3, 3);
+        assert_eq!(
+            verify_solution(&start, &goal, &[RIGHT, DOWN, RIGHT, DOWN]),
+            Ok(())
+        );
+        assert_eq!(
+            verify_solution(&start, &goal, &[RIGHT, UP]),
+            Err(SolutionError::IllegalMove(IllegalMove {
+                index: 1,
+                direction: UP
+            }))
+        );
+        let end = start.apply_moves(&[DOWN]).unwrap();
+        assert_eq!(
+            verify_solution(&start, &goal, &[DOWN]),
+            Err(SolutionError::WrongFinalState(end))
+        );
+        assert!(verify_solution(&start, &Goal::snail(3, 3), &[RIGHT, DOWN, RIGHT, DOWN]).is_err());
+
+        let start: Board = "3\n8 6 7\n2 5 4\n3 0 1".parse().unwrap();
+        for algo in [Algo::ASTAR, Algo::IDASTAR, Algo::PARALLELIDASTAR] {
+            let mut solver = Solver::new(3, 3);
+            let result = solver.solve(start.clone(), algo, Heuristics::LINCONFLICT);
+            assert_eq!(result.cost, 31);
+            assert_eq!(verify_solution(&start, &goal, &result.path), Ok(()));
+        }
     }
 }
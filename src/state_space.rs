@@ -0,0 +1,189 @@
+use crate::board::{Board, PuzzleState};
+use crate::goal::Goal;
+use crate::packed::PackedBoard;
+use crate::ranking;
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Above this, there are too many states to keep them all (3x4 already has 239 million)
+const MAX_CELLS: usize = 9;
+const UNVISITED: u8 = u8::MAX;
+
+// Exact distance to the goal of every solvable state of a small puzzle, found with a
+// backward BFS from the goal
+pub struct StateSpace {
+    goal: Goal,
+    // indexed by Board::rank, the other half of the states being unsolvable
+    distances: Vec<u8>,
+    histogram: Vec<usize>,
+}
+
+impl StateSpace {
+    // None when the puzzle has more than 9 cells
+    pub fn explore(goal: Goal) -> Option<Self> {
+        let (rows, cols) = goal.get_size();
+        if rows * cols > MAX_CELLS {
+            return None;
+        }
+        let start = PackedBoard::from_state(goal.get_board())?;
+        let mut distances = vec![UNVISITED; ranking::permutations(rows * cols, rows * cols)?];
+        distances[goal.get_board().rank()?] = 0;
+        let mut histogram = vec![1];
+        let mut queue = VecDeque::from([(start, 0)]);
+        while let Some((state, distance)) = queue.pop_front() {
+            for dir in state.next_directions() {
+                let mut next = state;
+                next.make_move(dir);
+                let index = next.rank()?;
+                if distances[index] != UNVISITED {
+                    continue;
+                }
+                distances[index] = distance as u8 + 1;
+                if histogram.len() == distance + 1 {
+                    histogram.push(0);
+                }
+                histogram[distance + 1] += 1;
+                queue.push_back((next, distance + 1));
+            }
+        }
+        Some(StateSpace {
+            goal,
+            distances,
+            histogram,
+        })
+    }
+
+    pub fn get_goal(&self) -> &Goal {
+        &self.goal
+    }
+
+    // Number of states which can reach the goal
+    pub fn len(&self) -> usize {
+        self.histogram.iter().sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Length of the optimal solution of the board, None if it is unsolvable or of another size
+    pub fn distance(&self, board: &Board) -> Option<usize> {
+        if (board.get_rows(), board.get_cols()) != self.goal.get_size() {
+            return None;
+        }
+        let distance = self.distances[board.rank()?];
+        (distance != UNVISITED).then_some(distance as usize)
+    }
+
+    // Number of states at every distance from the goal
+    pub fn histogram(&self) -> &[usize] {
+        &self.histogram
+    }
+
+    // Largest distance to the goal
+    pub fn diameter(&self) -> usize {
+        self.histogram.len() - 1
+    }
+
+    // States the furthest away from the goal, sorted by their tiles
+    pub fn antipodes(&self) -> Vec<Board> {
+        let (rows, cols) = self.goal.get_size();
+        let diameter = self.diameter() as u8;
+        // the order of the ranks is the order of the tiles
+        self.distances
+            .iter()
+            .enumerate()
+            .filter(|&(_, &distance)| distance == diameter)
+            .map(|(index, _)| Board::unrank(rows, cols, index).unwrap())
+            .collect()
+    }
+
+    // Writes every antipode in its own puzzle file "antipode-<rows>x<cols>-<number>.txt"
+    pub fn export_antipodes(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let (rows, cols) = self.goal.get_size();
+        fs::create_dir_all(dir)?;
+        let mut files = vec![];
+        for (i, board) in self.antipodes().iter().enumerate() {
+            let file = dir.join(format!("antipode-{rows}x{cols}-{}.txt", i + 1));
+            fs::write(&file, board.to_string())?;
+            files.push(file);
+        }
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Heuristics;
+    use crate::generator::Generator;
+    use crate::solver::{Algo, Solver};
+
+    #[test]
+    fn small_test() {
+        let space = StateSpace::explore(Goal::standard(2, 2)).unwrap();
+        assert_eq!(space.len(), 12);
+        assert_eq!(space.histogram(), [1, 2, 2, 2, 2, 2, 1]);
+        assert_eq!(space.diameter(), 6);
+        /*
+        0 3
+        2 1
+        */
+        assert_eq!(space.antipodes(), vec!["2\n0 3\n2 1".parse().unwrap()]);
+        assert_eq!(space.distance(&"2\n1 2\n0 3".parse().unwrap()), Some(1));
+        assert_eq!(space.distance(&"2\n2 1\n3 0".parse().unwrap()), None);
+        assert_eq!(space.distance(&Board::solved(3, 3)), None);
+
+        assert!(StateSpace::explore(Goal::standard(3, 4)).is_none());
+    }
+
+    #[test]
+    fn export_antipodes_test() {
+        let space = StateSpace::explore(Goal::standard(3, 3)).unwrap();
+        assert_eq!(space.len(), 181_440);
+        assert_eq!(space.histogram().iter().sum::<usize>(), 181_440);
+        assert_eq!(space.diameter(), 31);
+        assert_eq!(space.histogram()[31], 2);
+
+        let dir = std::env::temp_dir().join(format!("state-space-{}", std::process::id()));
+        let files = space.export_antipodes(&dir).unwrap();
+        assert_eq!(files.len(), 2);
+        let boards: Vec<Board> = files
+            .iter()
+            .map(|file| fs::read_to_string(file).unwrap().parse().unwrap())
+            .collect();
+        assert_eq!(boards, space.antipodes());
+        assert!(boards.contains(&"3\n8 6 7\n2 5 4\n3 0 1".parse().unwrap()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn optimal_solutions_test() {
+        // the solvers must find solutions as short as the exact distances
+        for goal in [
+            Goal::standard(3, 3),
+            Goal::snail(3, 3),
+            Goal::standard(2, 4),
+        ] {
+            let space = StateSpace::explore(goal.clone()).unwrap();
+            let mut generator = Generator::new(goal.clone(), 17);
+            let mut boards: Vec<Board> = (0..10).map(|_| generator.random_board()).collect();
+            // the snail goal has 148 antipodes
+            boards.extend(space.antipodes().into_iter().take(3));
+            for board in boards {
+                let distance = space.distance(&board).unwrap();
+                for algo in [Algo::ASTAR, Algo::IDASTAR, Algo::PARALLELIDASTAR] {
+                    let mut solver = Solver::with_goal(goal.clone());
+                    let result = solver.solve(board.clone(), algo, Heuristics::LINCONFLICT);
+                    assert_eq!(result.cost, distance, "{algo:?}\n{board}");
+                }
+                let mut solver = Solver::with_goal(goal.clone());
+                let result = solver.solve(board.clone(), Algo::ASTAR, Heuristics::LINCONFLICTPLUS);
+                assert_eq!(result.cost, distance, "{board}");
+            }
+        }
+    }
+}
@@ -0,0 +1,311 @@
+use crate::board::{Directions, PuzzleState};
+use crate::goal::Goal;
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, OnceLock, RwLock};
+
+// Up to 4 lines of 4 tiles: a count fits in 4 bits and a whole state (lines x lines counts and
+// the line of the blank) in a u128, and the tables of the 15-puzzle are built in an instant,
+// while the ones of the 24-puzzle would take minutes
+const MAX_LINES: usize = 4;
+
+// Walking distance along one axis. The tiles are only told apart by the line (row for the
+// vertical distance, column for the horizontal one) of their goal position: a state is the
+// number of tiles of every line going to every other line, and the line of the blank. A move
+// takes any tile of a line next to the blank into its line. The exact number of moves needed
+// in this relaxed puzzle never overestimates the real one, and the distances of the two axes
+// can be added as a vertical move never changes the horizontal state and vice versa.
+//
+// The states are numbered in the order of the BFS, and every state keeps the number of the
+// state reached by each move, so that a search follows them without encoding the counts.
+pub struct Table {
+    ids: HashMap<u128, u32>,
+    distances: Vec<u8>,
+    // state reached when the blank goes to the previous or next line, by the goal line of the
+    // tile it takes, NO_STATE when there is no such line or tile
+    next: Vec<[[u32; MAX_LINES]; 2]>,
+}
+
+const NO_STATE: u32 = u32::MAX;
+
+impl Table {
+    // Backward BFS from the goal state, where every line only holds its own tiles
+    fn build(lines: usize, line_length: usize, blank_line: usize) -> Self {
+        let mut counts = vec![0; lines * lines];
+        for line in 0..lines {
+            counts[line * lines + line] = line_length;
+        }
+        counts[blank_line * lines + blank_line] -= 1;
+
+        let mut table = Table {
+            ids: HashMap::from([(encode(&counts, blank_line), 0)]),
+            distances: vec![0],
+            next: vec![],
+        };
+        // the states are popped in the order of their numbers
+        let mut queue = VecDeque::from([(counts, blank_line)]);
+        while let Some((counts, blank)) = queue.pop_front() {
+            let distance = table.distances[table.next.len()];
+            let mut next = [[NO_STATE; MAX_LINES]; 2];
+            let neighbors = [blank.checked_sub(1), Some(blank + 1).filter(|&l| l < lines)];
+            for (side, next_blank) in neighbors.into_iter().enumerate() {
+                let Some(next_blank) = next_blank else {
+                    continue;
+                };
+                for goal_line in 0..lines {
+                    if counts[next_blank * lines + goal_line] == 0 {
+                        continue;
+                    }
+                    let mut moved = counts.clone();
+                    moved[next_blank * lines + goal_line] -= 1;
+                    moved[blank * lines + goal_line] += 1;
+                    let id = *table
+                        .ids
+                        .entry(encode(&moved, next_blank))
+                        .or_insert_with(|| {
+                            table.distances.push(distance + 1);
+                            queue.push_back((moved, next_blank));
+                            table.distances.len() as u32 - 1
+                        });
+                    next[side][goal_line] = id;
+                }
+            }
+            table.next.push(next);
+        }
+        table
+    }
+
+    pub fn len(&self) -> usize {
+        self.distances.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.distances.is_empty()
+    }
+
+    fn id(&self, counts: &[usize], blank_line: usize) -> u32 {
+        self.ids[&encode(counts, blank_line)]
+    }
+}
+
+fn encode(counts: &[usize], blank_line: usize) -> u128 {
+    counts
+        .iter()
+        .fold(blank_line as u128, |key, &count| key << 4 | count as u128)
+}
+
+// Tables are identified by their number of lines, length of the lines and goal line of the blank
+type TableKey = (usize, usize, usize);
+
+static TABLES: OnceLock<RwLock<HashMap<TableKey, Arc<Table>>>> = OnceLock::new();
+
+// Table built on first use and shared by every search, None when the lines are too many or
+// too long
+pub fn table(lines: usize, line_length: usize, blank_line: usize) -> Option<Arc<Table>> {
+    if lines > MAX_LINES || line_length > MAX_LINES {
+        return None;
+    }
+    let tables = TABLES.get_or_init(|| RwLock::new(HashMap::new()));
+    let key = (lines, line_length, blank_line);
+    if let Some(table) = tables.read().unwrap().get(&key) {
+        return Some(table.clone());
+    }
+    let mut tables = tables.write().unwrap();
+    let table = tables
+        .entry(key)
+        .or_insert_with(|| Arc::new(Table::build(lines, line_length, blank_line)));
+    Some(table.clone())
+}
+
+// The tables of both axes for one goal, looked up once per search. A state of the board is
+// the pair of the numbers of its vertical and horizontal states.
+#[derive(Clone)]
+pub struct WalkingDistance {
+    goal: Goal,
+    vertical: Arc<Table>,
+    horizontal: Arc<Table>,
+}
+
+impl WalkingDistance {
+    // None when the board is too large
+    pub fn for_goal(goal: &Goal) -> Option<Self> {
+        let (rows, cols) = goal.get_size();
+        let (blank_row, blank_col) = goal.position(0);
+        Some(WalkingDistance {
+            goal: goal.clone(),
+            vertical: table(rows, cols, blank_row)?,
+            horizontal: table(cols, rows, blank_col)?,
+        })
+    }
+
+    // Vertical and horizontal states of the board
+    pub fn states(&self, state: &impl PuzzleState) -> (u32, u32) {
+        let (rows, cols) = (state.get_rows(), state.get_cols());
+        let mut row_counts = [0; MAX_LINES * MAX_LINES];
+        let mut col_counts = [0; MAX_LINES * MAX_LINES];
+        for row in 0..rows {
+            for col in 0..cols {
+                let tile = state.tile(row, col);
+                if tile != 0 {
+                    let (goal_row, goal_col) = self.goal.position(tile);
+                    row_counts[row * rows + goal_row] += 1;
+                    col_counts[col * cols + goal_col] += 1;
+                }
+            }
+        }
+        let (row, col) = state.get_pos_0();
+        (
+            self.vertical.id(&row_counts[..rows * rows], row),
+            self.horizontal.id(&col_counts[..cols * cols], col),
+        )
+    }
+
+    // Sum of the vertical and horizontal walking distances of the board
+    pub fn heuristic(&self, state: &impl PuzzleState) -> usize {
+        self.distance(self.states(state))
+    }
+
+    pub fn distance(&self, (vertical, horizontal): (u32, u32)) -> usize {
+        self.vertical.distances[vertical as usize] as usize
+            + self.horizontal.distances[horizontal as usize] as usize
+    }
+
+    // States once the blank made this move taking this tile: only the state of the axis of
+    // the move changes
+    pub fn make_move(
+        &self,
+        (vertical, horizontal): (u32, u32),
+        move_d: Directions,
+        tile: usize,
+    ) -> (u32, u32) {
+        let (goal_row, goal_col) = self.goal.position(tile);
+        match move_d {
+            Directions::UP => (
+                self.vertical.next[vertical as usize][0][goal_row],
+                horizontal,
+            ),
+            Directions::DOWN => (
+                self.vertical.next[vertical as usize][1][goal_row],
+                horizontal,
+            ),
+            Directions::LEFT => (
+                vertical,
+                self.horizontal.next[horizontal as usize][0][goal_col],
+            ),
+            Directions::RIGHT => (
+                vertical,
+                self.horizontal.next[horizontal as usize][1][goal_col],
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Board, Evaluator, Heuristics};
+    use crate::generator::{Generator, Rng};
+    use crate::ranking;
+    use crate::solver::{Algo, Solver};
+    use crate::state_space::StateSpace;
+
+    #[test]
+    fn table_test() {
+        // number of vertical states of the 15-puzzle
+        assert_eq!(table(4, 4, 3).unwrap().len(), 24964);
+        assert!(table(5, 5, 4).is_none());
+        assert!(table(3, 5, 2).is_none());
+    }
+
+    #[test]
+    fn heuristic_test() {
+        let walking_distance = WalkingDistance::for_goal(&Goal::standard(3, 3)).unwrap();
+        assert_eq!(walking_distance.heuristic(&Board::solved(3, 3)), 0);
+
+        /*
+        2 1 3
+        4 0 5
+        7 8 6
+        one vertical move for the 6, and three horizontal ones for the 2, 1 and 5
+        */
+        let board = Board::load_from_str(3, 3, "2 1 3\n4 0 5\n7 8 6").unwrap();
+        assert_eq!(walking_distance.heuristic(&board), 4);
+
+        /*
+        0 15 14 13
+        12 11 10 9
+        8 7 6 5
+        4 3 2 1
+        */
+        let board: Board = "4\n0 15 14 13\n12 11 10 9\n8 7 6 5\n4 3 2 1"
+            .parse()
+            .unwrap();
+        let goal = Goal::standard(4, 4);
+        let walking_distance = WalkingDistance::for_goal(&goal).unwrap().heuristic(&board);
+        assert!(walking_distance > board.heuristic(Heuristics::MANHATTAN, &goal));
+        assert!(walking_distance <= 80);
+
+        assert!(WalkingDistance::for_goal(&Goal::standard(10, 10)).is_none());
+    }
+
+    #[test]
+    fn admissible_test() {
+        // never above the exact distance of the 3x3 boards, for both goals
+        for goal in [Goal::standard(3, 3), Goal::snail(3, 3)] {
+            let space = StateSpace::explore(goal.clone()).unwrap();
+            let tables = WalkingDistance::for_goal(&goal).unwrap();
+            for index in (0..ranking::permutations(9, 9).unwrap()).step_by(7) {
+                let board = Board::unrank(3, 3, index).unwrap();
+                if let Some(distance) = space.distance(&board) {
+                    let walking_distance = tables.heuristic(&board);
+                    assert!(walking_distance <= distance, "{board}");
+                    let linear_conflict = board.heuristic(Heuristics::LINCONFLICT, &goal);
+                    let combined = board.heuristic(Heuristics::WALKINGDISTANCE, &goal);
+                    assert_eq!(combined, walking_distance.max(linear_conflict));
+                    assert!(combined <= distance, "{board}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn make_move_test() {
+        let goal = Goal::standard(4, 4);
+        let evaluator = Evaluator::new(Heuristics::WALKINGDISTANCE, &goal);
+        let mut board = Generator::new(goal.clone(), 11).random_board();
+        let mut estimate = evaluator.estimate(&board);
+        let mut rng = Rng::new(11);
+        for _ in 0..200 {
+            let directions = board.next_directions();
+            let dir = directions[rng.below(directions.len())];
+            estimate = evaluator.make_move(&mut board, dir, estimate);
+            assert_eq!(estimate, evaluator.estimate(&board));
+        }
+    }
+
+    #[test]
+    fn solver_test() {
+        let goal = Goal::standard(3, 3);
+        let space = StateSpace::explore(goal.clone()).unwrap();
+        let mut generator = Generator::new(goal.clone(), 23);
+        let mut boards: Vec<Board> = (0..5).map(|_| generator.random_board()).collect();
+        boards.extend(space.antipodes());
+        for board in boards {
+            for algo in [Algo::ASTAR, Algo::IDASTAR] {
+                let mut solver = Solver::with_goal(goal.clone());
+                let result = solver.solve(board.clone(), algo, Heuristics::WALKINGDISTANCE);
+                assert_eq!(
+                    Some(result.cost),
+                    space.distance(&board),
+                    "{algo:?}\n{board}"
+                );
+            }
+        }
+
+        // rectangular boards have a table per axis
+        let board = Board::load_from_str(3, 4, "9 5 7 0\n2 10 1 6\n11 4 8 3").unwrap();
+        let mut solver = Solver::new(3, 4);
+        let result = solver.solve(board, Algo::IDASTAR, Heuristics::WALKINGDISTANCE);
+        assert_eq!(result.cost, 32);
+    }
+}